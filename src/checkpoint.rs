@@ -0,0 +1,467 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use ndarray::{Array, ArrayD, Dimension, Ix0, IxDyn};
+
+use crate::{
+    model::Model,
+    optimize::{optimize::Optimize, optimizer::Optimizer},
+};
+
+const MAGIC: &[u8; 8] = b"ZDLCKPT\0";
+/// チェックポイントの形式のバージョン。形式を変えた場合は上げる
+pub const FORMAT_VERSION: u32 = 1;
+
+/// 名前のついた配列の列
+pub type NamedArrays = Vec<(String, ArrayD<f64>)>;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// チェックポイントのファイルではない
+    BadMagic,
+    /// このプログラムより新しい形式で保存されている
+    UnsupportedVersion(u32),
+    /// モデルやオプティマイザが必要とする値がチェックポイントにない
+    MissingEntry(String),
+    /// チェックポイントに、モデルが持たない値がある
+    UnexpectedEntry(String),
+    ShapeMismatch {
+        name: String,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    InvalidData(String),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "I/O error: {}", e),
+            CheckpointError::BadMagic => write!(f, "not a checkpoint file"),
+            CheckpointError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported checkpoint version {} (supported up to {})",
+                version, FORMAT_VERSION
+            ),
+            CheckpointError::MissingEntry(name) => write!(f, "missing entry `{}`", name),
+            CheckpointError::UnexpectedEntry(name) => write!(f, "unexpected entry `{}`", name),
+            CheckpointError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "shape mismatch for `{}`: expected {:?}, found {:?}",
+                name, expected, found
+            ),
+            CheckpointError::InvalidData(message) => write!(f, "invalid data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+/// モデルのパラメータ・バッファと、オプティマイザの内部状態のスナップショット
+///
+/// # Examples
+/// ```no_run
+/// # use ndarray_rand::rand_distr::Normal;
+/// # use zero_deeplearning::checkpoint::{Checkpoint, CheckpointError};
+/// # use zero_deeplearning::optimize::{momentum::Momentum, optimizer::Optimizer};
+/// # use zero_deeplearning::sequential::Sequential;
+/// # fn main() -> Result<(), CheckpointError> {
+/// # let mut network = Sequential::mlp(&[784, 50, 10], &Normal::new(0.0, 0.01).unwrap());
+/// # let mut optimizer = Optimizer::new(|| Momentum::new(0.01, 0.9));
+///     Checkpoint::from_model(&mut network)
+///         .with_optimizer(&optimizer)
+///         .save("two_layer_net.ckpt")?;
+///
+///     let checkpoint = Checkpoint::load("two_layer_net.ckpt")?;
+///     checkpoint.restore_model(&mut network)?;
+///     checkpoint.restore_optimizer(&mut optimizer)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Checkpoint {
+    pub version: u32,
+    pub parameters: NamedArrays,
+    pub buffers: NamedArrays,
+    /// (パラメータ名, そのパラメータを更新するOptimizeの内部状態)の列
+    pub optimizer_state: Vec<(String, NamedArrays)>,
+    /// 学習率スケジューラの状態。スケジューラを設定していない場合は空
+    pub scheduler_state: NamedArrays,
+}
+
+impl Checkpoint {
+    pub fn from_model(model: &mut impl Model) -> Self {
+        Checkpoint {
+            version: FORMAT_VERSION,
+            parameters: model
+                .parameters()
+                .into_iter()
+                .map(|param| (param.name, param.value.to_owned()))
+                .collect(),
+            buffers: model
+                .buffers()
+                .into_iter()
+                .map(|(name, buffer)| (name, buffer.to_owned()))
+                .collect(),
+            optimizer_state: Vec::new(),
+            scheduler_state: Vec::new(),
+        }
+    }
+    pub fn with_optimizer<O: Optimize<IxDyn>>(mut self, optimizer: &Optimizer<O>) -> Self {
+        self.optimizer_state = optimizer.state();
+        self.scheduler_state = optimizer.scheduler_state();
+        self
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+    /// from_bytesで読み込める形式で書き出す
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        write_named_arrays(writer, &self.parameters)?;
+        write_named_arrays(writer, &self.buffers)?;
+        writer.write_all(&(self.optimizer_state.len() as u32).to_le_bytes())?;
+        for (name, state) in self.optimizer_state.iter() {
+            write_string(writer, name)?;
+            write_named_arrays(writer, state)?;
+        }
+        write_named_arrays(writer, &self.scheduler_state)
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+    /// saveで書き出した内容から読み込む
+    ///
+    /// 長さや形状はファイルの残りの大きさと照らし合わせてから確保するため、壊れたファイルでもエラーを返す
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CheckpointError> {
+        let mut reader = ByteReader { bytes };
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(CheckpointError::BadMagic);
+        }
+        let version = reader.u32()?;
+        if version > FORMAT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+        let parameters = reader.named_arrays()?;
+        let buffers = reader.named_arrays()?;
+        let optimizer_state = (0..reader.u32()?)
+            .map(|_| Ok((reader.string()?, reader.named_arrays()?)))
+            .collect::<Result<_, CheckpointError>>()?;
+        let scheduler_state = reader.named_arrays()?;
+        if !reader.bytes.is_empty() {
+            return Err(CheckpointError::InvalidData(format!(
+                "{} trailing bytes",
+                reader.bytes.len()
+            )));
+        }
+        Ok(Checkpoint {
+            version,
+            parameters,
+            buffers,
+            optimizer_state,
+            scheduler_state,
+        })
+    }
+    /// モデルのパラメータとバッファを置き換える。名前と形状が全て一致しない場合は、モデルを変更せずにエラーを返す
+    pub fn restore_model(&self, model: &mut impl Model) -> Result<(), CheckpointError> {
+        // 全ての値を確認してから書き込み、途中で失敗してもモデルが変更されないようにする
+        let params = model
+            .parameters()
+            .into_iter()
+            .map(|param| (param.name, param.value.shape().to_vec()))
+            .collect::<Vec<_>>();
+        match_entries(&params, &self.parameters)?;
+        let buffers = model
+            .buffers()
+            .into_iter()
+            .map(|(name, buffer)| (name, buffer.shape().to_vec()))
+            .collect::<Vec<_>>();
+        match_entries(&buffers, &self.buffers)?;
+
+        let sources = match_entries(&params, &self.parameters)?;
+        for (mut param, source) in model.parameters().into_iter().zip(sources) {
+            param.value.assign(source);
+        }
+        let sources = match_entries(&buffers, &self.buffers)?;
+        for ((_, mut buffer), source) in model.buffers().into_iter().zip(sources) {
+            buffer.assign(source);
+        }
+        Ok(())
+    }
+    pub fn restore_optimizer<O: Optimize<IxDyn>>(
+        &self,
+        optimizer: &mut Optimizer<O>,
+    ) -> Result<(), CheckpointError> {
+        optimizer.load_state(&self.optimizer_state, &self.scheduler_state)
+    }
+}
+
+/// `targets`の各要素に対応する、名前と形状が一致する`entries`の配列を返す
+fn match_entries<'a>(
+    targets: &[(String, Vec<usize>)],
+    entries: &'a NamedArrays,
+) -> Result<Vec<&'a ArrayD<f64>>, CheckpointError> {
+    if let Some((name, _)) = entries
+        .iter()
+        .find(|(name, _)| !targets.iter().any(|(target, _)| target == name))
+    {
+        return Err(CheckpointError::UnexpectedEntry(name.clone()));
+    }
+    targets
+        .iter()
+        .map(|(name, shape)| {
+            let (_, entry) = entries
+                .iter()
+                .find(|(entry_name, _)| entry_name == name)
+                .ok_or_else(|| CheckpointError::MissingEntry(name.clone()))?;
+            if entry.shape() != shape.as_slice() {
+                return Err(CheckpointError::ShapeMismatch {
+                    name: name.clone(),
+                    expected: shape.clone(),
+                    found: entry.shape().to_vec(),
+                });
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Optimize::load_stateで、内部状態から名前が`name`の値を取り出す
+pub fn state_entry<D: Dimension>(
+    state: &[(String, ArrayD<f64>)],
+    name: &str,
+) -> Result<Array<f64, D>, CheckpointError> {
+    let (_, value) = state
+        .iter()
+        .find(|(entry_name, _)| entry_name == name)
+        .ok_or_else(|| CheckpointError::MissingEntry(name.to_string()))?;
+    value.clone().into_dimensionality::<D>().map_err(|_| {
+        CheckpointError::InvalidData(format!(
+            "`{}` has {} dimensions, which does not match the optimizer",
+            name,
+            value.ndim()
+        ))
+    })
+}
+
+/// load_stateで、内部状態から名前が`name`のスカラー値を取り出す
+pub fn state_scalar(state: &[(String, ArrayD<f64>)], name: &str) -> Result<f64, CheckpointError> {
+    Ok(state_entry::<Ix0>(state, name)?.into_scalar())
+}
+
+fn write_string(writer: &mut impl Write, s: &str) -> io::Result<()> {
+    writer.write_all(&(s.len() as u32).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+fn write_named_arrays(writer: &mut impl Write, arrays: &NamedArrays) -> io::Result<()> {
+    writer.write_all(&(arrays.len() as u32).to_le_bytes())?;
+    for (name, array) in arrays.iter() {
+        write_string(writer, name)?;
+        writer.write_all(&(array.ndim() as u32).to_le_bytes())?;
+        for &len in array.shape() {
+            writer.write_all(&(len as u64).to_le_bytes())?;
+        }
+        for x in array.iter() {
+            writer.write_all(&x.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// 読み込み中のファイルの残りの部分
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    /// 先頭からlenバイトを取り出す。残りが足りない場合はエラーを返す
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CheckpointError> {
+        if len > self.bytes.len() {
+            return Err(CheckpointError::InvalidData(format!(
+                "expected {} bytes, but only {} bytes remain",
+                len,
+                self.bytes.len()
+            )));
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+    fn u32(&mut self) -> Result<u32, CheckpointError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> Result<u64, CheckpointError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn string(&mut self) -> Result<String, CheckpointError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| CheckpointError::InvalidData(e.to_string()))
+    }
+    fn named_arrays(&mut self) -> Result<NamedArrays, CheckpointError> {
+        (0..self.u32()?)
+            .map(|_| {
+                let name = self.string()?;
+                let shape = (0..self.u32()?)
+                    .map(|_| {
+                        usize::try_from(self.u64()?).map_err(|_| {
+                            CheckpointError::InvalidData(format!("`{}` is too large", name))
+                        })
+                    })
+                    .collect::<Result<Vec<usize>, _>>()?;
+                let byte_len = shape
+                    .iter()
+                    .try_fold(8_usize, |acc, &len| acc.checked_mul(len))
+                    .ok_or_else(|| {
+                        CheckpointError::InvalidData(format!("`{}` is too large", name))
+                    })?;
+                let data = self
+                    .take(byte_len)?
+                    .chunks_exact(8)
+                    .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect::<Vec<f64>>();
+                let array = ArrayD::from_shape_vec(IxDyn(&shape), data)
+                    .map_err(|e| CheckpointError::InvalidData(e.to_string()))?;
+                Ok((name, array))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layer::{affine_layer::AffineLayer, batch_normalization_layer::BatchNormalizationLayer},
+        lr_scheduler::cosine_annealing_warm_restarts::CosineAnnealingWarmRestarts,
+        optimize::momentum::Momentum,
+        sequential::Sequential,
+    };
+    use ndarray::{arr1, arr2, stack, Array1, Axis};
+
+    fn new_network() -> Sequential {
+        let mut network = Sequential::new();
+        network
+            .add(AffineLayer::new_owned(
+                arr2(&[[0.2, -0.5], [0.4, 0.3]]),
+                arr1(&[0.1, 0.0]),
+            ))
+            .add(BatchNormalizationLayer::new_owned(
+                stack![Axis(0), Array1::ones(2), Array1::zeros(2)],
+                Array1::zeros(2),
+                Array1::ones(2),
+                0.9,
+            ));
+        network
+    }
+
+    fn new_optimizer() -> Optimizer<Momentum<IxDyn>> {
+        Optimizer::new(|| Momentum::new(0.1, 0.9))
+            .with_scheduler(CosineAnnealingWarmRestarts::new(0.1, 0.0, 2, 2))
+    }
+
+    fn train(network: &mut Sequential, optimizer: &mut Optimizer<Momentum<IxDyn>>, steps: usize) {
+        let x = arr2(&[[1.0, 2.0], [-1.0, 0.5], [0.3, -0.8]]);
+        let t = Array1::from(vec![0, 1, 1]);
+        for _ in 0..steps {
+            network.gradient(&x, &t);
+            optimizer.step(network);
+        }
+    }
+
+    fn to_bytes(checkpoint: &Checkpoint) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        checkpoint.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn resumed_training_matches_uninterrupted_training() {
+        let (mut network, mut optimizer) = (new_network(), new_optimizer());
+        train(&mut network, &mut optimizer, 3);
+        let bytes = to_bytes(&Checkpoint::from_model(&mut network).with_optimizer(&optimizer));
+
+        let checkpoint = Checkpoint::from_bytes(&bytes).unwrap();
+        let (mut resumed, mut resumed_optimizer) = (new_network(), new_optimizer());
+        checkpoint.restore_model(&mut resumed).unwrap();
+        checkpoint
+            .restore_optimizer(&mut resumed_optimizer)
+            .unwrap();
+        // スケジューラも周期の途中から再開する
+        assert_eq!(resumed_optimizer.learning_rate(), optimizer.learning_rate());
+
+        train(&mut network, &mut optimizer, 4);
+        train(&mut resumed, &mut resumed_optimizer, 4);
+        let expected = Checkpoint::from_model(&mut network).with_optimizer(&optimizer);
+        let actual = Checkpoint::from_model(&mut resumed).with_optimizer(&resumed_optimizer);
+        assert_eq!(actual.parameters, expected.parameters);
+        assert_eq!(actual.buffers, expected.buffers);
+        assert_eq!(actual.optimizer_state, expected.optimizer_state);
+        assert_eq!(actual.scheduler_state, expected.scheduler_state);
+    }
+
+    #[test]
+    fn mismatched_buffer_leaves_model_unchanged() {
+        let mut checkpoint = Checkpoint::from_model(&mut new_network());
+        checkpoint.parameters[0].1.fill(7.0);
+        checkpoint.buffers[0].1 = ArrayD::zeros(IxDyn(&[3]));
+        let mut network = new_network();
+        assert!(matches!(
+            checkpoint.restore_model(&mut network),
+            Err(CheckpointError::ShapeMismatch { .. })
+        ));
+        assert_eq!(network.parameters()[0].value[[0, 0]], 0.2);
+    }
+
+    #[test]
+    fn huge_lengths_are_rejected_without_allocating() {
+        let mut bytes = to_bytes(&Checkpoint::from_model(&mut new_network()));
+        bytes.truncate(MAGIC.len() + 4);
+        // パラメータが1つあり、その名前の長さがu32の最大値
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Checkpoint::from_bytes(&bytes),
+            Err(CheckpointError::InvalidData(_))
+        ));
+
+        // 形状の積がusizeに収まらない
+        bytes.truncate(MAGIC.len() + 8);
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.push(b'w');
+        bytes.extend_from_slice(&2_u32.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            Checkpoint::from_bytes(&bytes),
+            Err(CheckpointError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let bytes = to_bytes(&Checkpoint::from_model(&mut new_network()));
+        for len in [0, 5, bytes.len() - 1] {
+            assert!(Checkpoint::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    sync::{
+        mpsc::{sync_channel, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use ndarray::prelude::*;
+use ndarray_rand::rand::{self, rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::labels::Labels;
+
+/// (入力データ, 正解ラベル)の組からミニバッチを取り出す
+///
+/// epochを呼ぶたびに、全てのデータを1回ずつ含むミニバッチのイテレータを返す。
+/// データはArcで保持するため、cloneしてもコピーは起こらない。
+#[derive(Clone)]
+pub struct DataLoader<L: Labels> {
+    x: Arc<Array2<f64>>,
+    t: Arc<L>,
+    batch_size: usize,
+    shuffle: bool,
+    drop_last: bool,
+    /// バックグラウンドのスレッドで先読みしておくミニバッチの数
+    prefetch: Option<usize>,
+    rng: StdRng,
+}
+
+impl<L: Labels + Send + Sync + 'static> DataLoader<L> {
+    /// エポックごとにシャッフルするDataLoaderを作る。シードはランダムに選ぶ
+    ///
+    /// xの行数とtのラベルの数が異なる場合や、batch_sizeが0の場合はpanicする。
+    pub fn new(x: Array2<f64>, t: L, batch_size: usize) -> Self {
+        assert_eq!(
+            x.nrows(),
+            t.batch_size(),
+            "number of samples and labels must match"
+        );
+        assert!(batch_size > 0, "batch_size must be positive");
+        DataLoader {
+            x: Arc::new(x),
+            t: Arc::new(t),
+            batch_size,
+            shuffle: true,
+            drop_last: false,
+            prefetch: None,
+            rng: StdRng::seed_from_u64(rand::thread_rng().gen()),
+        }
+    }
+    /// シャッフルに使う乱数のシードを固定する
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+    /// falseの場合、シャッフルせずに先頭から順に取り出す
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+    /// trueの場合、batch_sizeに満たない最後のミニバッチを捨てる
+    ///
+    /// データの数がbatch_sizeより少ないと1つもミニバッチを作れないため、panicする。
+    pub fn with_drop_last(mut self, drop_last: bool) -> Self {
+        assert!(
+            !drop_last || self.num_samples() >= self.batch_size,
+            "drop_last requires at least batch_size ({}) samples, but there are {}",
+            self.batch_size,
+            self.num_samples()
+        );
+        self.drop_last = drop_last;
+        self
+    }
+    /// バックグラウンドのスレッドで、最大batches個のミニバッチを先読みする
+    pub fn with_prefetch(mut self, batches: usize) -> Self {
+        self.prefetch = Some(batches);
+        self
+    }
+    /// データの数
+    pub fn num_samples(&self) -> usize {
+        self.x.nrows()
+    }
+    /// 1エポックあたりのミニバッチの数
+    pub fn num_batches(&self) -> usize {
+        if self.drop_last {
+            self.num_samples() / self.batch_size
+        } else {
+            self.num_samples().div_ceil(self.batch_size)
+        }
+    }
+    /// 次のエポックのミニバッチのイテレータを返す
+    pub fn epoch(&mut self) -> Batches<L> {
+        let mut indexes = (0..self.num_samples()).collect::<Vec<_>>();
+        if self.shuffle {
+            indexes.shuffle(&mut self.rng);
+        }
+        indexes.truncate(self.num_batches() * self.batch_size);
+        let mut sampler = Sampler {
+            x: self.x.clone(),
+            t: self.t.clone(),
+            indexes,
+            batch_size: self.batch_size,
+            position: 0,
+        };
+        let remaining = self.num_batches();
+        match self.prefetch {
+            None => Batches {
+                source: Source::Direct(sampler),
+                remaining,
+            },
+            Some(batches) => {
+                let (sender, receiver) = sync_channel(batches);
+                // 受け取り側が破棄されると送信に失敗し、スレッドが終了する
+                thread::spawn(move || {
+                    for batch in sampler.by_ref() {
+                        if sender.send(batch).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Batches {
+                    source: Source::Prefetch(receiver),
+                    remaining,
+                }
+            }
+        }
+    }
+}
+
+/// 1エポック分のミニバッチのイテレータ。要素は(入力データ, 正解ラベル)
+pub struct Batches<L> {
+    source: Source<L>,
+    remaining: usize,
+}
+
+enum Source<L> {
+    Direct(Sampler<L>),
+    Prefetch(Receiver<(Array2<f64>, L)>),
+}
+
+impl<L: Labels> Iterator for Batches<L> {
+    type Item = (Array2<f64>, L);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = match &mut self.source {
+            Source::Direct(sampler) => sampler.next(),
+            Source::Prefetch(receiver) => receiver.recv().ok(),
+        }?;
+        self.remaining -= 1;
+        Some(batch)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<L: Labels> ExactSizeIterator for Batches<L> {}
+
+struct Sampler<L> {
+    x: Arc<Array2<f64>>,
+    t: Arc<L>,
+    indexes: Vec<usize>,
+    batch_size: usize,
+    position: usize,
+}
+
+impl<L: Labels> Iterator for Sampler<L> {
+    type Item = (Array2<f64>, L);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.indexes.len() {
+            return None;
+        }
+        let end = (self.position + self.batch_size).min(self.indexes.len());
+        let batch_mask = &self.indexes[self.position..end];
+        self.position = end;
+        Some((
+            self.x.select(Axis(0), batch_mask),
+            self.t.select_rows(batch_mask),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader(num_samples: usize, batch_size: usize) -> DataLoader<Array1<usize>> {
+        let x = Array2::from_shape_fn((num_samples, 2), |(i, j)| (i * 2 + j) as f64);
+        DataLoader::new(x, Array1::from_iter(0..num_samples), batch_size)
+    }
+
+    /// 1エポック分のミニバッチを、各ミニバッチのラベル(=元のインデックス)の列として取り出す
+    fn epoch_indexes(loader: &mut DataLoader<Array1<usize>>) -> Vec<Vec<usize>> {
+        loader
+            .epoch()
+            .map(|(x, t)| {
+                for (row, &i) in x.outer_iter().zip(t.iter()) {
+                    assert_eq!(row[0], (i * 2) as f64);
+                }
+                t.to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_epoch_covers_all_samples_once() {
+        let mut loader = loader(10, 3).with_seed(0);
+        let batches = epoch_indexes(&mut loader);
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![3, 3, 3, 1]
+        );
+        let mut all = batches.concat();
+        assert_ne!(all, (0..10).collect::<Vec<_>>());
+        all.sort();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+        // エポックごとに異なる順序でシャッフルする
+        assert_ne!(epoch_indexes(&mut loader), batches);
+    }
+
+    #[test]
+    fn seeded_order_is_reproducible_with_and_without_prefetch() {
+        let direct = epoch_indexes(&mut loader(10, 4).with_seed(7));
+        let mut prefetched = loader(10, 4).with_seed(7).with_prefetch(2);
+        let batches = prefetched.epoch();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches.map(|(_, t)| t.to_vec()).collect::<Vec<_>>(), direct);
+    }
+
+    #[test]
+    fn drop_last_and_no_shuffle() {
+        let mut loader = loader(10, 4).with_shuffle(false).with_drop_last(true);
+        assert_eq!(loader.num_batches(), 2);
+        assert_eq!(
+            epoch_indexes(&mut loader),
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "drop_last requires at least batch_size (4) samples, but there are 3"
+    )]
+    fn drop_last_with_too_few_samples_is_rejected() {
+        loader(3, 4).with_drop_last(true);
+    }
+
+    #[test]
+    #[should_panic(expected = "number of samples and labels must match")]
+    fn mismatched_labels_are_rejected() {
+        DataLoader::new(Array2::zeros((3, 2)), Array1::<usize>::zeros(2), 1);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod params;
+pub mod search;
+pub mod search_space;
+pub mod successive_halving;
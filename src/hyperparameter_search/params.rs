@@ -0,0 +1,119 @@
+use std::fmt;
+
+/// ハイパーパラメータの値
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    Float(f64),
+    Int(i64),
+    Text(String),
+}
+
+impl ParamValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ParamValue::Float(v) => Some(*v),
+            ParamValue::Int(v) => Some(*v as f64),
+            ParamValue::Text(_) => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            ParamValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ParamValue::Text(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamValue::Float(v) => write!(f, "{}", v),
+            ParamValue::Int(v) => write!(f, "{}", v),
+            ParamValue::Text(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl From<f64> for ParamValue {
+    fn from(v: f64) -> Self {
+        ParamValue::Float(v)
+    }
+}
+
+impl From<i64> for ParamValue {
+    fn from(v: i64) -> Self {
+        ParamValue::Int(v)
+    }
+}
+
+impl From<usize> for ParamValue {
+    fn from(v: usize) -> Self {
+        ParamValue::Int(v as i64)
+    }
+}
+
+impl From<&str> for ParamValue {
+    fn from(v: &str) -> Self {
+        ParamValue::Text(v.to_string())
+    }
+}
+
+/// 1回の試行で用いるハイパーパラメータの組。探索空間で定義した順に並ぶ
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Params {
+    values: Vec<(String, ParamValue)>,
+}
+
+impl Params {
+    pub fn new(values: Vec<(String, ParamValue)>) -> Self {
+        Params { values }
+    }
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.values.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ParamValue)> {
+        self.values.iter().map(|(n, v)| (n.as_str(), v))
+    }
+    /// # Panics
+    ///
+    /// nameのパラメータがない、または数値でない場合
+    pub fn f64(&self, name: &str) -> f64 {
+        self.get(name)
+            .and_then(ParamValue::as_f64)
+            .unwrap_or_else(|| panic!("no numeric hyperparameter `{}`", name))
+    }
+    /// # Panics
+    ///
+    /// nameのパラメータがない、または0以上の整数でない場合
+    pub fn usize(&self, name: &str) -> usize {
+        self.get(name)
+            .and_then(ParamValue::as_i64)
+            .and_then(|v| usize::try_from(v).ok())
+            .unwrap_or_else(|| panic!("no non-negative integer hyperparameter `{}`", name))
+    }
+    /// # Panics
+    ///
+    /// nameのパラメータがない、または文字列でない場合
+    pub fn str(&self, name: &str) -> &str {
+        self.get(name)
+            .and_then(ParamValue::as_str)
+            .unwrap_or_else(|| panic!("no string hyperparameter `{}`", name))
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self
+            .values
+            .iter()
+            .map(|(n, v)| format!("{}: {}", n, v))
+            .collect::<Vec<_>>();
+        write!(f, "{}", values.join(", "))
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use super::{params::Params, search_space::SearchSpace, successive_halving::SuccessiveHalving};
+
+/// ハイパーパラメータの組の選び方
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// 探索空間からランダムにtrials回選ぶ
+    Random { trials: usize, seed: u64 },
+    /// 連続な範囲をそれぞれpoints個に等分し、全ての組み合わせを試す
+    Grid { points: usize },
+}
+
+/// 評価指標の良い方向
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Minimize,
+    Maximize,
+}
+
+impl Direction {
+    /// aの方が良ければLessを返す。NaN(発散した試行など)は最も悪いとする
+    pub fn compare(self, a: f64, b: f64) -> Ordering {
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => match self {
+                Direction::Minimize => a.total_cmp(&b),
+                Direction::Maximize => b.total_cmp(&a),
+            },
+        }
+    }
+}
+
+/// 1回の試行の結果
+#[derive(Clone, Debug)]
+pub struct Trial {
+    /// 試行した順番(0始まり)
+    pub index: usize,
+    pub params: Params,
+    pub metric: f64,
+    /// 評価指標を得たときまでに使った予算(エポック数など)。予算を区切らない探索ではNone
+    pub budget: Option<usize>,
+}
+
+/// 探索空間と選び方を決めて、ハイパーパラメータを探索する
+///
+/// # Examples
+/// ```no_run
+/// # use zero_deeplearning::hyperparameter_search::{
+/// #     search::{HyperparameterSearch, Strategy},
+/// #     search_space::SearchSpace,
+/// # };
+/// # fn train_and_validate(learning_rate: f64) -> f64 {
+/// #     (learning_rate.log10() + 3.0).abs()
+/// # }
+/// # fn main() -> std::io::Result<()> {
+/// # let space = SearchSpace::new().log_uniform("learning_rate", 1e-6, 1e-2);
+/// let results = HyperparameterSearch::new(space, Strategy::Random { trials: 20, seed: 0 })
+///     .minimize("val_loss")
+///     .run(|params| train_and_validate(params.f64("learning_rate")));
+/// results.write_csv("hyperparameter_search.csv")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct HyperparameterSearch {
+    space: SearchSpace,
+    strategy: Strategy,
+    metric_name: String,
+    direction: Direction,
+}
+
+impl HyperparameterSearch {
+    /// 既定では、"metric"という名前の評価指標を最小化する
+    pub fn new(space: SearchSpace, strategy: Strategy) -> Self {
+        HyperparameterSearch {
+            space,
+            strategy,
+            metric_name: "metric".to_string(),
+            direction: Direction::Minimize,
+        }
+    }
+    /// 評価指標nameが小さいほど良いとする
+    pub fn minimize(mut self, name: impl Into<String>) -> Self {
+        self.metric_name = name.into();
+        self.direction = Direction::Minimize;
+        self
+    }
+    /// 評価指標nameが大きいほど良いとする
+    pub fn maximize(mut self, name: impl Into<String>) -> Self {
+        self.metric_name = name.into();
+        self.direction = Direction::Maximize;
+        self
+    }
+    /// 試行するハイパーパラメータの組
+    pub fn candidates(&self) -> Vec<Params> {
+        match self.strategy {
+            Strategy::Random { trials, seed } => self.space.random(trials, seed),
+            Strategy::Grid { points } => self.space.grid(points),
+        }
+    }
+    /// 各組でobjectiveを呼び、返された評価指標の良い順に並べた結果を返す
+    pub fn run(&self, mut objective: impl FnMut(&Params) -> f64) -> SearchResults {
+        let trials = self
+            .candidates()
+            .into_iter()
+            .enumerate()
+            .map(|(index, params)| {
+                let metric = objective(&params);
+                Trial {
+                    index,
+                    params,
+                    metric,
+                    budget: None,
+                }
+            })
+            .collect();
+        self.results(trials)
+    }
+    /// 選んだ組を逐次半減法で評価する
+    ///
+    /// createで各組の学習の状態を作り、trainで追加の予算の分だけ学習を進めて評価指標を返す。
+    /// 評価指標の悪い組は少ない予算で打ち切り、残った組は途中から学習を続ける。
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use ndarray::{arr1, arr2, Array1, IxDyn};
+    /// # use ndarray_rand::rand_distr::Normal;
+    /// # use zero_deeplearning::{
+    /// #     data_loader::DataLoader,
+    /// #     hyperparameter_search::{
+    /// #         search::{HyperparameterSearch, Strategy},
+    /// #         search_space::SearchSpace,
+    /// #         successive_halving::SuccessiveHalving,
+    /// #     },
+    /// #     labels::Labels,
+    /// #     optimize::{optimizer::Optimizer, sgd::SGD},
+    /// #     sequential::Sequential,
+    /// #     trainer::trainer::Trainer,
+    /// # };
+    /// # fn create_trainer(learning_rate: f64) -> Trainer<Sequential, SGD<IxDyn>, Array1<usize>> {
+    /// #     let network = Sequential::mlp(&[2, 4, 2], &Normal::new(0.0, 0.1).unwrap());
+    /// #     let optimizer = Optimizer::new(move || SGD::new(learning_rate));
+    /// #     let loader = DataLoader::new(arr2(&[[0.0, 1.0], [1.0, 0.0]]), arr1(&[1, 0]), 2);
+    /// #     Trainer::new(network, optimizer, Labels::loss_layer, loader)
+    /// # }
+    /// # let (x_val, t_val) = (arr2(&[[0.0, 1.0], [1.0, 0.0]]), arr1(&[1, 0]));
+    /// # let space = SearchSpace::new().log_uniform("learning_rate", 1e-6, 1e-2);
+    /// let results = HyperparameterSearch::new(space, Strategy::Random { trials: 20, seed: 0 })
+    ///     .minimize("val_loss")
+    ///     .run_successive_halving(
+    ///         SuccessiveHalving::new(1, 9),
+    ///         |params| create_trainer(params.f64("learning_rate")),
+    ///         |trainer, epochs| {
+    ///             trainer.fit(epochs);
+    ///             trainer.evaluate(&x_val, &t_val).loss
+    ///         },
+    ///     );
+    /// ```
+    pub fn run_successive_halving<T>(
+        &self,
+        halving: SuccessiveHalving,
+        mut create: impl FnMut(&Params) -> T,
+        mut train: impl FnMut(&mut T, usize) -> f64,
+    ) -> SearchResults {
+        let trials = halving.run(
+            self.candidates(),
+            0,
+            0,
+            self.direction,
+            &mut create,
+            &mut train,
+        );
+        self.results(trials)
+    }
+    /// Hyperbandで探索する。組は選び方によらず、探索空間からseedを元にランダムに選ぶ
+    ///
+    /// create, trainはrun_successive_halvingと同じ。
+    pub fn run_hyperband<T>(
+        &self,
+        halving: SuccessiveHalving,
+        seed: u64,
+        create: impl FnMut(&Params) -> T,
+        train: impl FnMut(&mut T, usize) -> f64,
+    ) -> SearchResults {
+        let trials = halving.hyperband(&self.space, seed, self.direction, create, train);
+        self.results(trials)
+    }
+    fn results(&self, trials: Vec<Trial>) -> SearchResults {
+        SearchResults::new(
+            self.space.names().map(str::to_string).collect(),
+            self.metric_name.clone(),
+            self.direction,
+            trials,
+        )
+    }
+}
+
+/// 探索結果。trialsは評価指標の良い順に並ぶ
+#[derive(Clone, Debug)]
+pub struct SearchResults {
+    pub param_names: Vec<String>,
+    pub metric_name: String,
+    pub direction: Direction,
+    pub trials: Vec<Trial>,
+}
+
+impl SearchResults {
+    /// trialsを評価指標の良い順に並べる。NaN(発散した試行など)は最後にする
+    ///
+    /// 予算が異なる試行の評価指標は比べられないため、多くの予算を使った試行を先にする
+    pub fn new(
+        param_names: Vec<String>,
+        metric_name: String,
+        direction: Direction,
+        mut trials: Vec<Trial>,
+    ) -> Self {
+        trials.sort_by(|a, b| {
+            b.budget
+                .cmp(&a.budget)
+                .then_with(|| direction.compare(a.metric, b.metric))
+        });
+        SearchResults {
+            param_names,
+            metric_name,
+            direction,
+            trials,
+        }
+    }
+    /// 最も良かった試行
+    pub fn best(&self) -> Option<&Trial> {
+        self.trials.first()
+    }
+    /// 全ての試行を、"trial,各パラメータ,評価指標"の列のCSVとして書き出す
+    ///
+    /// 予算を記録した試行がある場合は、最後に"budget"の列を加える
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let with_budget = self.trials.iter().any(|trial| trial.budget.is_some());
+        let header = std::iter::once("trial")
+            .chain(self.param_names.iter().map(String::as_str))
+            .chain(std::iter::once(self.metric_name.as_str()))
+            .chain(with_budget.then_some("budget"))
+            .map(csv_field)
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", header.join(","))?;
+        for trial in self.trials.iter() {
+            let row = std::iter::once(trial.index.to_string())
+                .chain(self.param_names.iter().map(|name| {
+                    trial
+                        .params
+                        .get(name)
+                        .map_or(String::new(), |v| csv_field(&v.to_string()))
+                }))
+                .chain(std::iter::once(trial.metric.to_string()))
+                .chain(with_budget.then(|| trial.budget.map_or(String::new(), |b| b.to_string())))
+                .collect::<Vec<_>>();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        writer.flush()
+    }
+}
+
+/// カンマや引用符、改行を含む場合は、引用符で囲む
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyperparameter_search::params::ParamValue;
+
+    fn trial(index: usize, metric: f64, budget: Option<usize>) -> Trial {
+        Trial {
+            index,
+            params: Params::new(Vec::new()),
+            metric,
+            budget,
+        }
+    }
+
+    #[test]
+    fn nan_is_worst_in_both_directions() {
+        for direction in [Direction::Minimize, Direction::Maximize] {
+            assert_eq!(direction.compare(f64::NAN, 1.0), Ordering::Greater);
+            assert_eq!(direction.compare(1.0, f64::NAN), Ordering::Less);
+            assert_eq!(direction.compare(f64::NAN, f64::NAN), Ordering::Equal);
+            assert_eq!(
+                direction.compare(f64::NAN, f64::INFINITY),
+                Ordering::Greater
+            );
+        }
+        assert_eq!(Direction::Minimize.compare(0.1, 0.2), Ordering::Less);
+        assert_eq!(Direction::Maximize.compare(0.1, 0.2), Ordering::Greater);
+    }
+
+    #[test]
+    fn results_are_sorted_by_budget_then_metric() {
+        let results = SearchResults::new(
+            Vec::new(),
+            "val_loss".to_string(),
+            Direction::Minimize,
+            vec![
+                trial(0, 0.1, Some(1)),
+                trial(1, f64::NAN, Some(9)),
+                trial(2, 0.5, Some(9)),
+                trial(3, 0.2, Some(3)),
+                trial(4, 0.3, Some(9)),
+                trial(5, 0.05, None),
+            ],
+        );
+        assert_eq!(
+            results.trials.iter().map(|t| t.index).collect::<Vec<_>>(),
+            vec![4, 2, 1, 3, 0, 5]
+        );
+        assert_eq!(results.best().unwrap().index, 4);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("learning_rate"), "learning_rate");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn writes_results_as_csv() {
+        let space =
+            SearchSpace::new().choice("activation", ["relu", "leaky,relu"].map(ParamValue::from));
+        let results = HyperparameterSearch::new(space, Strategy::Grid { points: 2 })
+            .maximize("val_acc")
+            .run(|params| {
+                if params.str("activation") == "relu" {
+                    0.5
+                } else {
+                    0.75
+                }
+            });
+        let path = std::env::temp_dir().join(format!("{}_search.csv", std::process::id()));
+        results.write_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            csv,
+            "trial,activation,val_acc\n1,\"leaky,relu\",0.75\n0,relu,0.5\n"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use ndarray_rand::rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::params::{ParamValue, Params};
+
+/// 1つのハイパーパラメータの探索範囲
+#[derive(Clone, Debug, PartialEq)]
+pub enum Domain {
+    /// 対数が[low, high)で一様になるように選ぶ。学習率などに使う
+    LogUniform { low: f64, high: f64 },
+    /// [low, high)から一様に選ぶ
+    Uniform { low: f64, high: f64 },
+    /// 候補の中から選ぶ
+    Choice(Vec<ParamValue>),
+}
+
+impl Domain {
+    fn sample(&self, rng: &mut StdRng) -> ParamValue {
+        match self {
+            Domain::LogUniform { low, high } => {
+                ParamValue::Float(rng.gen_range(low.ln()..high.ln()).exp())
+            }
+            Domain::Uniform { low, high } => ParamValue::Float(rng.gen_range(*low..*high)),
+            Domain::Choice(values) => values[rng.gen_range(0..values.len())].clone(),
+        }
+    }
+    /// グリッドサーチで用いる値。連続な範囲は両端を含めてpoints個に等分する
+    fn grid(&self, points: usize) -> Vec<ParamValue> {
+        let linspace = |low: f64, high: f64| -> Vec<f64> {
+            if points <= 1 {
+                return vec![(low + high) / 2.0];
+            }
+            (0..points)
+                .map(|i| low + (high - low) * i as f64 / (points - 1) as f64)
+                .collect()
+        };
+        match self {
+            Domain::LogUniform { low, high } => linspace(low.ln(), high.ln())
+                .into_iter()
+                .map(|v| ParamValue::Float(v.exp()))
+                .collect(),
+            Domain::Uniform { low, high } => linspace(*low, *high)
+                .into_iter()
+                .map(ParamValue::Float)
+                .collect(),
+            Domain::Choice(values) => values.clone(),
+        }
+    }
+}
+
+/// 探索するハイパーパラメータと、その範囲
+///
+/// # Examples
+/// ```
+/// # use zero_deeplearning::hyperparameter_search::{
+/// #     params::ParamValue, search_space::SearchSpace,
+/// # };
+/// let space = SearchSpace::new()
+///     .log_uniform("learning_rate", 1e-6, 1e-2)
+///     .choice("hidden_size", [50usize, 100].map(ParamValue::from));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SearchSpace {
+    domains: Vec<(String, Domain)>,
+}
+
+impl SearchSpace {
+    pub fn new() -> Self {
+        SearchSpace::default()
+    }
+    pub fn log_uniform(mut self, name: impl Into<String>, low: f64, high: f64) -> Self {
+        let name = name.into();
+        assert!(
+            0.0 < low && low < high && high.is_finite(),
+            "log_uniform `{}` requires 0 < low < high < inf, got [{}, {})",
+            name,
+            low,
+            high
+        );
+        self.domains.push((name, Domain::LogUniform { low, high }));
+        self
+    }
+    pub fn uniform(mut self, name: impl Into<String>, low: f64, high: f64) -> Self {
+        let name = name.into();
+        assert!(
+            low < high && low.is_finite() && high.is_finite(),
+            "uniform `{}` requires finite low < high, got [{}, {})",
+            name,
+            low,
+            high
+        );
+        self.domains.push((name, Domain::Uniform { low, high }));
+        self
+    }
+    pub fn choice(
+        mut self,
+        name: impl Into<String>,
+        values: impl IntoIterator<Item = ParamValue>,
+    ) -> Self {
+        let name = name.into();
+        let values = values.into_iter().collect::<Vec<_>>();
+        assert!(
+            !values.is_empty(),
+            "choice `{}` requires at least one value",
+            name
+        );
+        self.domains.push((name, Domain::Choice(values)));
+        self
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.domains.iter().map(|(name, _)| name.as_str())
+    }
+    /// ランダムにtrials組のハイパーパラメータを選ぶ
+    pub fn random(&self, trials: usize, seed: u64) -> Vec<Params> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..trials)
+            .map(|_| {
+                Params::new(
+                    self.domains
+                        .iter()
+                        .map(|(name, domain)| (name.clone(), domain.sample(&mut rng)))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+    /// 全てのハイパーパラメータの値の組み合わせを返す。後に定義したものほど速く変化する
+    pub fn grid(&self, points: usize) -> Vec<Params> {
+        let mut combinations = vec![Vec::new()];
+        for (name, domain) in self.domains.iter() {
+            let values = domain.grid(points);
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination: Vec<(String, ParamValue)>| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.clone(), value.clone()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations.into_iter().map(Params::new).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space() -> SearchSpace {
+        SearchSpace::new()
+            .log_uniform("learning_rate", 1e-4, 1e-1)
+            .uniform("dropout", 0.0, 0.5)
+            .choice("hidden_size", [50usize, 100].map(ParamValue::from))
+    }
+
+    #[test]
+    fn later_params_vary_fastest_in_grid() {
+        let grid = space().grid(2);
+        assert_eq!(grid.len(), 8);
+        let values = grid
+            .iter()
+            .map(|params| {
+                (
+                    params.f64("learning_rate"),
+                    params.f64("dropout"),
+                    params.usize("hidden_size"),
+                )
+            })
+            .collect::<Vec<_>>();
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12 * b.abs().max(1.0);
+        for (i, &(learning_rate, dropout, hidden_size)) in values.iter().enumerate() {
+            assert!(close(learning_rate, [1e-4, 1e-1][i / 4]));
+            assert!(close(dropout, [0.0, 0.5][i / 2 % 2]));
+            assert_eq!(hidden_size, [50, 100][i % 2]);
+        }
+        // 対数が等間隔になる
+        let learning_rates = SearchSpace::new()
+            .log_uniform("learning_rate", 1e-4, 1e-2)
+            .grid(3);
+        assert!(close(learning_rates[1].f64("learning_rate"), 1e-3));
+        assert_eq!(
+            SearchSpace::new().uniform("x", 0.0, 1.0).grid(1)[0].f64("x"),
+            0.5
+        );
+    }
+
+    #[test]
+    fn random_samples_stay_in_bounds_and_are_reproducible() {
+        let samples = space().random(200, 42);
+        for params in samples.iter() {
+            let learning_rate = params.f64("learning_rate");
+            assert!((1e-4..1e-1).contains(&learning_rate));
+            assert!((0.0..0.5).contains(&params.f64("dropout")));
+            assert!([50, 100].contains(&params.usize("hidden_size")));
+        }
+        // 対数で一様なので、1e-4から1e-1のうち最初の1桁に約1/3が入る
+        let small = samples
+            .iter()
+            .filter(|p| p.f64("learning_rate") < 1e-3)
+            .count();
+        assert!((45..90).contains(&small), "{}", small);
+
+        let same = space().random(200, 42);
+        assert!(samples
+            .iter()
+            .zip(same.iter())
+            .all(|(a, b)| a.to_string() == b.to_string()));
+        let other = space().random(200, 43);
+        assert!(samples
+            .iter()
+            .zip(other.iter())
+            .any(|(a, b)| a.to_string() != b.to_string()));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "log_uniform `learning_rate` requires 0 < low < high < inf, got [0, 0.1)"
+    )]
+    fn log_uniform_rejects_non_positive_bounds() {
+        SearchSpace::new().log_uniform("learning_rate", 0.0, 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "uniform `dropout` requires finite low < high, got [0.5, 0.5)")]
+    fn uniform_rejects_empty_range() {
+        SearchSpace::new().uniform("dropout", 0.5, 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "choice `hidden_size` requires at least one value")]
+    fn choice_rejects_no_values() {
+        SearchSpace::new().choice("hidden_size", []);
+    }
+}
@@ -0,0 +1,237 @@
+use super::{
+    params::Params,
+    search::{Direction, Trial},
+    search_space::SearchSpace,
+};
+
+/// 逐次半減法(Successive Halving)の予算の割り当て方
+///
+/// 多くの組を少ない予算(エポック数など)で学習し、評価指標の良い上位1/etaだけを残して
+/// 予算をeta倍に増やすことを、max_budgetに達するまで繰り返す。
+/// 残った組は途中まで学習した状態から学習を続けるため、最初から学習し直すことはない。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SuccessiveHalving {
+    min_budget: usize,
+    max_budget: usize,
+    eta: usize,
+}
+
+impl SuccessiveHalving {
+    /// 既定では、各段階で上位1/3を残す
+    pub fn new(min_budget: usize, max_budget: usize) -> Self {
+        assert!(
+            0 < min_budget && min_budget <= max_budget,
+            "budget must satisfy 0 < min_budget <= max_budget"
+        );
+        SuccessiveHalving {
+            min_budget,
+            max_budget,
+            eta: 3,
+        }
+    }
+    /// 各段階で残す割合を1/etaにする
+    pub fn with_eta(mut self, eta: usize) -> Self {
+        assert!(eta >= 2, "eta must be at least 2");
+        self.eta = eta;
+        self
+    }
+    /// start_budgetから始めたときの、各段階の予算
+    pub fn budgets(&self, start_budget: usize) -> Vec<usize> {
+        let mut budgets = Vec::new();
+        let mut budget = start_budget.clamp(self.min_budget, self.max_budget);
+        while budget < self.max_budget {
+            budgets.push(budget);
+            budget = budget.saturating_mul(self.eta);
+        }
+        budgets.push(self.max_budget);
+        budgets
+    }
+    /// Hyperbandの各ブラケットの(組の数, 最初の予算)
+    ///
+    /// 多くの組を少ない予算から始めるブラケットから、少ない組を最初からmax_budgetで学習するブラケットまでを並べる
+    pub fn brackets(&self) -> Vec<(usize, usize)> {
+        let mut s_max = 0;
+        while self.min_budget * self.eta.pow(s_max + 1) <= self.max_budget {
+            s_max += 1;
+        }
+        (0..=s_max)
+            .rev()
+            .map(|s| {
+                let scale = self.eta.pow(s);
+                let n = ((s_max + 1) as usize * scale).div_ceil(s as usize + 1);
+                (n, (self.max_budget / scale).max(self.min_budget))
+            })
+            .collect()
+    }
+    /// candidatesを逐次半減法で評価する
+    ///
+    /// createで各組の学習の状態(Trainerなど)を作り、trainで追加の予算の分だけ学習を進めて評価指標を返す。
+    /// 各組の結果は、打ち切られたとき(または最後まで残ったとき)の評価指標と予算になる。
+    /// first_indexは、最初の組に付ける試行の番号。
+    pub fn run<T>(
+        &self,
+        candidates: Vec<Params>,
+        start_budget: usize,
+        first_index: usize,
+        direction: Direction,
+        create: &mut impl FnMut(&Params) -> T,
+        train: &mut impl FnMut(&mut T, usize) -> f64,
+    ) -> Vec<Trial> {
+        let mut finished = Vec::new();
+        let mut alive = candidates
+            .into_iter()
+            .enumerate()
+            .map(|(i, params)| {
+                let state = create(&params);
+                let trial = Trial {
+                    index: first_index + i,
+                    params,
+                    metric: f64::NAN,
+                    budget: Some(0),
+                };
+                (trial, state)
+            })
+            .collect::<Vec<_>>();
+        let budgets = self.budgets(start_budget);
+        for (rung, &budget) in budgets.iter().enumerate() {
+            for (trial, state) in alive.iter_mut() {
+                let trained = trial.budget.unwrap_or(0);
+                trial.metric = train(state, budget - trained);
+                trial.budget = Some(budget);
+            }
+            if rung + 1 == budgets.len() {
+                break;
+            }
+            alive.sort_by(|(a, _), (b, _)| direction.compare(a.metric, b.metric));
+            let keep = (alive.len() / self.eta).max(1);
+            // 打ち切った組の学習の状態はここで破棄する
+            finished.extend(alive.drain(keep..).map(|(trial, _)| trial));
+        }
+        finished.extend(alive.into_iter().map(|(trial, _)| trial));
+        finished.sort_by_key(|trial| trial.index);
+        finished
+    }
+    /// Hyperbandで探索する
+    ///
+    /// ブラケットごとにspaceからランダムに組を選び、逐次半減法で評価する。
+    /// 少ない予算では良し悪しを判断しにくい場合にも、最初から多くの予算を使うブラケットで補う。
+    pub fn hyperband<T>(
+        &self,
+        space: &SearchSpace,
+        seed: u64,
+        direction: Direction,
+        mut create: impl FnMut(&Params) -> T,
+        mut train: impl FnMut(&mut T, usize) -> f64,
+    ) -> Vec<Trial> {
+        let mut trials = Vec::new();
+        for (bracket, (n, start_budget)) in self.brackets().into_iter().enumerate() {
+            let candidates = space.random(n, seed.wrapping_add(bracket as u64));
+            trials.extend(self.run(
+                candidates,
+                start_budget,
+                trials.len(),
+                direction,
+                &mut create,
+                &mut train,
+            ));
+        }
+        trials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyperparameter_search::params::ParamValue;
+
+    /// 学習の状態の代わりに、(評価指標, これまでに受け取った予算)を持つ
+    type FakeState = (f64, Vec<usize>);
+
+    fn candidates(metrics: &[f64]) -> Vec<Params> {
+        metrics
+            .iter()
+            .map(|&m| Params::new(vec![("metric".to_string(), ParamValue::Float(m))]))
+            .collect()
+    }
+
+    #[test]
+    fn budgets_and_brackets() {
+        let halving = SuccessiveHalving::new(1, 9).with_eta(3);
+        assert_eq!(halving.budgets(1), vec![1, 3, 9]);
+        assert_eq!(halving.budgets(3), vec![3, 9]);
+        assert_eq!(halving.budgets(9), vec![9]);
+        assert_eq!(SuccessiveHalving::new(2, 10).budgets(2), vec![2, 6, 10]);
+        assert_eq!(halving.brackets(), vec![(9, 1), (5, 3), (3, 9)]);
+        assert_eq!(SuccessiveHalving::new(4, 4).brackets(), vec![(1, 4)]);
+    }
+
+    #[test]
+    fn survivors_continue_training_without_being_recreated() {
+        let halving = SuccessiveHalving::new(1, 9).with_eta(3);
+        let metrics = [0.9, 0.4, f64::NAN, 0.7, 0.1, 0.8, 0.5, 0.3, 0.6];
+        let mut created = 0;
+        let mut states = Vec::new();
+        let trials = halving.run(
+            candidates(&metrics),
+            1,
+            10,
+            Direction::Minimize,
+            &mut |params| {
+                created += 1;
+                (params.f64("metric"), Vec::new())
+            },
+            &mut |state: &mut FakeState, budget| {
+                state.1.push(budget);
+                states.push(state.clone());
+                state.0
+            },
+        );
+        assert_eq!(created, 9);
+        // 9組を1エポック、上位3組を追加で2エポック、最良の1組を追加で6エポック学習する
+        assert_eq!(states.len(), 9 + 3 + 1);
+        assert_eq!(states.last().unwrap(), &(0.1, vec![1, 2, 6]));
+
+        assert_eq!(
+            trials.iter().map(|t| t.index).collect::<Vec<_>>(),
+            (10..19).collect::<Vec<_>>()
+        );
+        let budget_of = |metric: f64| {
+            trials
+                .iter()
+                .find(|t| t.metric.to_bits() == metric.to_bits())
+                .and_then(|t| t.budget)
+        };
+        assert_eq!(budget_of(0.1), Some(9));
+        assert_eq!(budget_of(0.3), Some(3));
+        assert_eq!(budget_of(0.4), Some(3));
+        assert_eq!(budget_of(0.5), Some(1));
+        assert_eq!(budget_of(f64::NAN), Some(1));
+    }
+
+    #[test]
+    fn hyperband_runs_every_bracket() {
+        let space = SearchSpace::new().uniform("metric", 0.0, 1.0);
+        let mut created = 0;
+        let trials = SuccessiveHalving::new(1, 9).hyperband(
+            &space,
+            0,
+            Direction::Minimize,
+            |params| {
+                created += 1;
+                (params.f64("metric"), Vec::new())
+            },
+            |state: &mut FakeState, budget| {
+                state.1.push(budget);
+                state.0
+            },
+        );
+        assert_eq!(created, 9 + 5 + 3);
+        assert_eq!(
+            trials.iter().map(|t| t.index).collect::<Vec<_>>(),
+            (0..17).collect::<Vec<_>>()
+        );
+        let max_budget = trials.iter().filter(|t| t.budget == Some(9)).count();
+        // 各ブラケットで1組ずつ最後まで残り、最後のブラケットは全ての組を9エポック学習する
+        assert_eq!(max_budget, 1 + 1 + 3);
+    }
+}
@@ -0,0 +1,86 @@
+use ndarray::prelude::*;
+
+use crate::{
+    layer::{
+        layer::Layer, softmax_with_loss_layer::SoftmaxWithLossLayer,
+        sparse_softmax_with_loss_layer::SparseSoftmaxWithLossLayer,
+    },
+    subfunction::argmax::argmax,
+};
+
+/// 正解ラベルの表し方
+///
+/// one-hot形式(Array2<f64>)とクラスのインデックス(Array1<usize>)のどちらでも、
+/// 同じloss、accuracy、gradientで学習できるようにする。
+pub trait Labels {
+    /// ラベルの数(バッチサイズ)
+    fn batch_size(&self) -> usize;
+    /// i番目のラベルのクラスのインデックス
+    fn class_index(&self, i: usize) -> usize;
+    /// ラベルに合ったsoftmax + 交差エントロピー誤差の層
+    fn loss_layer(&self) -> Box<dyn Layer<Array2<f64>, f64>>;
+    /// 指定したインデックスのラベルを取り出す(ミニバッチの作成に使う)
+    fn select_rows(&self, indexes: &[usize]) -> Self
+    where
+        Self: Sized;
+}
+
+impl Labels for Array2<f64> {
+    fn batch_size(&self) -> usize {
+        self.nrows()
+    }
+    fn class_index(&self, i: usize) -> usize {
+        argmax(self.row(i))
+    }
+    fn loss_layer(&self) -> Box<dyn Layer<Array2<f64>, f64>> {
+        Box::new(SoftmaxWithLossLayer::new(self))
+    }
+    fn select_rows(&self, indexes: &[usize]) -> Self {
+        self.select(Axis(0), indexes)
+    }
+}
+
+impl Labels for Array1<usize> {
+    fn batch_size(&self) -> usize {
+        self.len()
+    }
+    fn class_index(&self, i: usize) -> usize {
+        self[i]
+    }
+    fn loss_layer(&self) -> Box<dyn Layer<Array2<f64>, f64>> {
+        Box::new(SparseSoftmaxWithLossLayer::new(self))
+    }
+    fn select_rows(&self, indexes: &[usize]) -> Self {
+        self.select(Axis(0), indexes)
+    }
+}
+
+/// 予測結果yのうち、最大の出力のクラスが正解ラベルtと一致する割合
+pub fn accuracy(y: &Array2<f64>, t: &impl Labels) -> f64 {
+    let count = (0..y.nrows())
+        .filter(|&row| argmax(y.row(row)) == t.class_index(row))
+        .count();
+    count as f64 / y.nrows() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subfunction::one_hot::one_hot;
+
+    #[test]
+    fn accuracy_is_the_same_for_indexes_and_one_hot() {
+        let y = arr2(&[
+            [0.1, 0.7, 0.2],
+            [0.5, 0.3, 0.2],
+            [0.2, 0.2, 0.6],
+            [0.9, 0.0, 0.1],
+        ]);
+        let t = arr1(&[1, 0, 1, 2]);
+        assert_eq!(accuracy(&y, &t), 0.5);
+        assert_eq!(accuracy(&y, &one_hot(&t, 3)), 0.5);
+        let selected = t.select_rows(&[2, 0]);
+        assert_eq!(selected, arr1(&[1, 1]));
+        assert_eq!(one_hot(&t, 3).select_rows(&[2, 0]), one_hot(&selected, 3));
+    }
+}
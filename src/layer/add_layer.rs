@@ -13,6 +13,12 @@ impl<Dim: Dimension> AddLayer<Dim> {
     }
 }
 
+impl<Dim: Dimension> Default for AddLayer<Dim> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dim: Dimension> Layer<(Array<f64, Dim>, Array<f64, Dim>), Array<f64, Dim>> for AddLayer<Dim> {
     fn forward(&mut self, (x, y): &(Array<f64, Dim>, Array<f64, Dim>)) -> Array<f64, Dim> {
         x + y
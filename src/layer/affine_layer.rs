@@ -1,12 +1,13 @@
-use crate::layer::layer::Layer;
+use crate::layer::{layer::Layer, sequential_layer::SequentialLayer};
+use crate::model::Parameter;
 use ndarray::{
     prelude::{Array1, Array2},
-    Axis,
+    Axis, CowArray, Ix1, Ix2,
 };
 
 pub struct AffineLayer<'a> {
-    w: &'a Array2<f64>,
-    b: &'a Array1<f64>,
+    w: CowArray<'a, f64, Ix2>,
+    b: CowArray<'a, f64, Ix1>,
     x: Array2<f64>,
     pub dw: Array2<f64>,
     pub db: Array1<f64>,
@@ -14,6 +15,9 @@ pub struct AffineLayer<'a> {
 
 impl<'a> AffineLayer<'a> {
     pub fn new(w: &'a Array2<f64>, b: &'a Array1<f64>) -> Self {
+        Self::from_cow(w.view().into(), b.view().into())
+    }
+    fn from_cow(w: CowArray<'a, f64, Ix2>, b: CowArray<'a, f64, Ix1>) -> Self {
         AffineLayer {
             w,
             b,
@@ -24,10 +28,17 @@ impl<'a> AffineLayer<'a> {
     }
 }
 
+impl AffineLayer<'static> {
+    /// パラメータを借用せずに所有する層を作る
+    pub fn new_owned(w: Array2<f64>, b: Array1<f64>) -> Self {
+        Self::from_cow(w.into(), b.into())
+    }
+}
+
 impl<'a> Layer<Array2<f64>, Array2<f64>> for AffineLayer<'a> {
     fn forward(&mut self, x: &Array2<f64>) -> Array2<f64> {
         self.x = x.clone();
-        x.dot(self.w) + self.b
+        x.dot(&self.w) + &self.b
     }
     fn backward(&mut self, dout: &Array2<f64>) -> Array2<f64> {
         self.dw = self.x.t().dot(dout);
@@ -35,3 +46,20 @@ impl<'a> Layer<Array2<f64>, Array2<f64>> for AffineLayer<'a> {
         dout.dot(&self.w.t())
     }
 }
+
+impl SequentialLayer for AffineLayer<'static> {
+    fn parameters(&mut self) -> Vec<Parameter<'_>> {
+        vec![
+            Parameter::new(
+                "w",
+                self.w.view_mut().into_dyn(),
+                self.dw.view_mut().into_dyn(),
+            ),
+            Parameter::new(
+                "b",
+                self.b.view_mut().into_dyn(),
+                self.db.view_mut().into_dyn(),
+            ),
+        ]
+    }
+}
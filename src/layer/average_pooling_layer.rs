@@ -0,0 +1,106 @@
+use crate::layer::layer::Layer;
+use crate::subfunction::{
+    col2im::col2im,
+    im2col::{im2col, output_size},
+};
+use ndarray::{Array2, Array4, Axis};
+
+pub struct AveragePoolingLayer {
+    pool_h: usize,
+    pool_w: usize,
+    stride: usize,
+    pad: usize,
+    x_shape: (usize, usize, usize, usize),
+}
+
+impl AveragePoolingLayer {
+    /// プーリングの幅や高さ、strideが0の場合はpanicする
+    pub fn new(pool_h: usize, pool_w: usize, stride: usize, pad: usize) -> Self {
+        assert!(pool_h > 0 && pool_w > 0, "pool size must be positive");
+        assert!(stride > 0, "stride must be positive");
+        AveragePoolingLayer {
+            pool_h,
+            pool_w,
+            stride,
+            pad,
+            x_shape: (0, 0, 0, 0),
+        }
+    }
+}
+
+impl Layer<Array4<f64>, Array4<f64>> for AveragePoolingLayer {
+    fn forward(&mut self, x: &Array4<f64>) -> Array4<f64> {
+        let (n, c, h, w) = x.dim();
+        let out_h = output_size(h, self.pool_h, self.stride, self.pad);
+        let out_w = output_size(w, self.pool_w, self.stride, self.pad);
+
+        self.x_shape = x.dim();
+        // 1行が1チャンネル分のプーリング領域になるように並べる
+        // パディング部分の0も平均の計算に含める
+        let col = im2col(x.view(), self.pool_h, self.pool_w, self.stride, self.pad)
+            .into_shape((n * out_h * out_w * c, self.pool_h * self.pool_w))
+            .unwrap();
+        let out = col.mean_axis(Axis(1)).unwrap();
+        out.into_shape((n, out_h, out_w, c))
+            .unwrap()
+            .permuted_axes([0, 3, 1, 2])
+            .as_standard_layout()
+            .into_owned()
+    }
+    fn backward(&mut self, dout: &Array4<f64>) -> Array4<f64> {
+        let (n, c, out_h, out_w) = dout.dim();
+        let pool_size = self.pool_h * self.pool_w;
+        let dout = dout
+            .view()
+            .permuted_axes([0, 2, 3, 1])
+            .as_standard_layout()
+            .into_owned()
+            .into_shape((n * out_h * out_w * c, 1))
+            .unwrap();
+
+        // 勾配はプーリング領域の全ての位置に均等に分配する
+        let davg = Array2::from_elem((dout.len(), pool_size), 1.0 / pool_size as f64) * &dout;
+        let dcol = davg.into_shape((n * out_h * out_w, c * pool_size)).unwrap();
+        col2im(
+            dcol.view(),
+            self.x_shape,
+            self.pool_h,
+            self.pool_w,
+            self.stride,
+            self.pad,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr2, Array};
+
+    #[test]
+    fn gradient_is_spread_evenly() {
+        let x = Array::range(0.0, 16.0, 1.0)
+            .into_shape((1, 1, 4, 4))
+            .unwrap();
+        let mut layer = AveragePoolingLayer::new(2, 2, 2, 0);
+        let y = layer.forward(&x);
+        assert_eq!(
+            y.index_axis(Axis(0), 0).index_axis(Axis(0), 0),
+            arr2(&[[2.5, 4.5], [10.5, 12.5]])
+        );
+        let dx = layer.backward(&Array4::ones((1, 1, 2, 2)));
+        assert_eq!(dx, Array4::from_elem((1, 1, 4, 4), 0.25));
+    }
+
+    #[test]
+    #[should_panic(expected = "filter size 3 is larger than the padded input size 2")]
+    fn pool_larger_than_input_is_rejected() {
+        AveragePoolingLayer::new(3, 3, 1, 0).forward(&Array4::zeros((1, 1, 2, 2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "pool size must be positive")]
+    fn zero_pool_size_is_rejected() {
+        AveragePoolingLayer::new(0, 2, 1, 0);
+    }
+}
@@ -1,69 +1,212 @@
-use crate::layer::layer::Layer;
-use ndarray::{Array1, Array2, Axis};
+use crate::layer::{layer::Layer, sequential_layer::SequentialLayer};
+use crate::model::Parameter;
+use ndarray::{stack, Array1, Array2, ArrayViewMutD, Axis, CowArray, Ix2};
 pub struct BatchNormalizationLayer<'a> {
-    aff: &'a Array1<f64>, // [gamma, beta]
-    pub daff: Array1<f64>,
-    input_size: usize,
-    xhat: Array2<f64>,
-    u9: Array2<f64>,
-    u8: Array2<f64>,
-    u7: Array2<f64>,
-    u6: Array2<f64>,
-    u5: Array2<f64>,
-    u4: Array2<f64>,
-    u3: Array2<f64>,
-    u2: Array2<f64>,
-    u1: Array2<f64>,
+    aff: CowArray<'a, f64, Ix2>, // [[gamma...], [beta...]]、形状は(2, 入力の次元数)
+    pub daff: Array2<f64>,
+    pub running_mean: Array1<f64>,
+    pub running_var: Array1<f64>,
+    momentum: f64,
+    train_flg: bool,
+    batch_size: usize,
+    xc: Array2<f64>,
+    std: Array1<f64>,
+    xn: Array2<f64>,
 }
 
 impl<'a> BatchNormalizationLayer<'a> {
-    pub fn new(input_size: usize, aff: &'a Array1<f64>) -> Self {
+    pub fn new(
+        aff: &'a Array2<f64>,
+        running_mean: Array1<f64>,
+        running_var: Array1<f64>,
+        momentum: f64,
+    ) -> Self {
+        Self::from_cow(aff.view().into(), running_mean, running_var, momentum)
+    }
+    fn from_cow(
+        aff: CowArray<'a, f64, Ix2>,
+        running_mean: Array1<f64>,
+        running_var: Array1<f64>,
+        momentum: f64,
+    ) -> Self {
         Self {
+            daff: Array2::zeros(aff.raw_dim()),
             aff,
-            daff: Array1::zeros(2),
-            input_size,
-            xhat: Array2::zeros((0, 0)),
-            u9: Array2::zeros((0, 0)),
-            u8: Array2::zeros((0, 0)),
-            u7: Array2::zeros((0, 0)),
-            u6: Array2::zeros((0, 0)),
-            u5: Array2::zeros((0, 0)),
-            u4: Array2::zeros((0, 0)),
-            u3: Array2::zeros((0, 0)),
-            u2: Array2::zeros((0, 0)),
-            u1: Array2::zeros((0, 0)),
+            running_mean,
+            running_var,
+            momentum,
+            train_flg: true,
+            batch_size: 0,
+            xc: Array2::zeros((0, 0)),
+            std: Array1::zeros(0),
+            xn: Array2::zeros((0, 0)),
         }
     }
+    /// 学習時はミニバッチの平均・分散で正規化し、移動平均を更新する。
+    /// 推論時は移動平均を用いて正規化する。
+    pub fn set_train_flg(&mut self, train_flg: bool) {
+        self.train_flg = train_flg;
+    }
+}
+
+impl BatchNormalizationLayer<'static> {
+    /// パラメータを借用せずに所有する層を作る
+    pub fn new_owned(
+        aff: Array2<f64>,
+        running_mean: Array1<f64>,
+        running_var: Array1<f64>,
+        momentum: f64,
+    ) -> Self {
+        Self::from_cow(aff.into(), running_mean, running_var, momentum)
+    }
 }
 
 impl<'a> Layer<Array2<f64>, Array2<f64>> for BatchNormalizationLayer<'a> {
     fn forward(&mut self, x: &Array2<f64>) -> Array2<f64> {
-        self.u9 = x.clone();
-        self.u8 = (x.sum_axis(Axis(1)) / self.input_size as f64).insert_axis(Axis(1));
-        self.u7 = x.clone();
-        self.u6 = x - &self.u8;
-        self.u5 = self.u6.mapv(|v| v * v);
-        self.u4 = (self.u5.sum_axis(Axis(1)) / self.input_size as f64).insert_axis(Axis(1));
-        self.u3 = self.u4.mapv(|v| (v + 1.0e-7).sqrt());
-        self.u2 = 1.0 / &self.u3;
-        self.u1 = self.u6.clone();
-        self.xhat = &self.u6 * &self.u2;
-        let y = &self.xhat * self.aff[0] + self.aff[1];
-        y
+        let gamma = self.aff.index_axis(Axis(0), 0);
+        let beta = self.aff.index_axis(Axis(0), 1);
+        let xn = if self.train_flg {
+            let mu = x.mean_axis(Axis(0)).unwrap();
+            let xc = x - &mu;
+            let var = xc.mapv(|v| v * v).mean_axis(Axis(0)).unwrap();
+            let std = var.mapv(|v| (v + 1.0e-7).sqrt());
+            let xn = &xc / &std;
+
+            self.batch_size = x.shape()[0];
+            self.xc = xc;
+            self.std = std;
+            self.running_mean = self.momentum * &self.running_mean + (1.0 - self.momentum) * &mu;
+            self.running_var = self.momentum * &self.running_var + (1.0 - self.momentum) * &var;
+            xn
+        } else {
+            let xc = x - &self.running_mean;
+            xc / &self.running_var.mapv(|v| (v + 1.0e-7).sqrt())
+        };
+        self.xn = xn;
+        &self.xn * &gamma + beta
     }
     fn backward(&mut self, dout: &Array2<f64>) -> Array2<f64> {
-        self.daff[0] = (&self.xhat * dout).sum();
-        self.daff[1] = dout.sum();
-        let dxhat = dout * self.aff[0];
-        let du1 = &dxhat * &self.u2;
-        let du2 = (&dxhat * &self.u1).sum_axis(Axis(1));
-        let du3 = -&self.u2 * &self.u2 * dout;
-        let du4 = &du3 / (2.0 * &self.u3);
-        let du5 = Array2::from_elem(self.u5.raw_dim(), 1.0) * &du4;
-        let du6 = 2.0 * &self.u6 * &du5;
-        let du7 = &du1 + &du6;
-        let du8 = -&du7;
-        let du9 = Array2::from_elem(self.u9.raw_dim(), 1.0) * &du8;
-        &du7 + &du9
+        let gamma = self.aff.index_axis(Axis(0), 0);
+        let dbeta = dout.sum_axis(Axis(0));
+        let dgamma = (&self.xn * dout).sum_axis(Axis(0));
+        self.daff = stack![Axis(0), dgamma, dbeta];
+
+        let dxn = dout * &gamma;
+        let mut dxc = &dxn / &self.std;
+        let dstd = -(&dxn * &self.xc / (&self.std * &self.std)).sum_axis(Axis(0));
+        let dvar = 0.5 * dstd / &self.std;
+        dxc = dxc + (2.0 / self.batch_size as f64) * &self.xc * &dvar;
+        let dmu = dxc.sum_axis(Axis(0));
+        dxc - dmu / self.batch_size as f64
+    }
+}
+
+impl SequentialLayer for BatchNormalizationLayer<'static> {
+    fn parameters(&mut self) -> Vec<Parameter<'_>> {
+        vec![Parameter::new(
+            "aff",
+            self.aff.view_mut().into_dyn(),
+            self.daff.view_mut().into_dyn(),
+        )]
+    }
+    fn buffers(&mut self) -> Vec<(String, ArrayViewMutD<'_, f64>)> {
+        vec![
+            (
+                "running_mean".to_string(),
+                self.running_mean.view_mut().into_dyn(),
+            ),
+            (
+                "running_var".to_string(),
+                self.running_var.view_mut().into_dyn(),
+            ),
+        ]
+    }
+    fn set_train_flg(&mut self, train_flg: bool) {
+        self.train_flg = train_flg;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subfunction::numerical_gradient::numerical_gradient;
+    use ndarray::{arr1, arr2, ArrayView2};
+
+    fn layer() -> BatchNormalizationLayer<'static> {
+        BatchNormalizationLayer::new_owned(
+            stack![Axis(0), Array1::ones(2), Array1::zeros(2)],
+            Array1::zeros(2),
+            Array1::ones(2),
+            0.9,
+        )
+    }
+
+    #[test]
+    fn normalizes_each_feature_and_updates_running_statistics() {
+        let mut layer = layer();
+        // 列ごとに平均と分散が異なる入力
+        let x = arr2(&[[1.0, 10.0], [3.0, 30.0], [5.0, 50.0]]);
+        let y = layer.forward(&x);
+        for column in y.columns() {
+            assert!(column.mean().unwrap().abs() < 1e-12);
+            assert!((column.mapv(|v| v * v).mean().unwrap() - 1.0).abs() < 1e-6);
+        }
+        let (mean, var) = (arr1(&[3.0, 30.0]), arr1(&[8.0 / 3.0, 800.0 / 3.0]));
+        let expected_mean = 0.1 * &mean;
+        let expected_var = 0.9 + 0.1 * &var;
+        assert!((&layer.running_mean - &expected_mean)
+            .iter()
+            .all(|d| d.abs() < 1e-12));
+        assert!((&layer.running_var - &expected_var)
+            .iter()
+            .all(|d| d.abs() < 1e-12));
+    }
+
+    #[test]
+    fn inference_uses_running_statistics() {
+        let mut layer = layer();
+        layer.running_mean = arr1(&[1.0, -1.0]);
+        layer.running_var = arr1(&[4.0, 0.25]);
+        layer.set_train_flg(false);
+        let y = layer.forward(&arr2(&[[3.0, 0.0]]));
+        assert!((y[[0, 0]] - 1.0).abs() < 1e-6);
+        assert!((y[[0, 1]] - 2.0).abs() < 1e-6);
+        // 推論では移動平均を更新しない
+        assert_eq!(layer.running_mean, arr1(&[1.0, -1.0]));
+    }
+
+    #[test]
+    fn backward_matches_numerical_gradient() {
+        let x = arr2(&[
+            [1.0, -2.0, 0.5],
+            [0.3, 4.0, -1.0],
+            [2.0, 1.0, 0.0],
+            [-1.5, 0.2, 3.0],
+        ]);
+        let aff = arr2(&[[1.5, 0.5, -1.0], [0.1, -0.2, 0.3]]);
+        let dout = arr2(&[
+            [0.1, -0.3, 0.7],
+            [1.0, 0.2, -0.5],
+            [-0.4, 0.9, 0.3],
+            [0.6, -1.1, 0.8],
+        ]);
+        let loss = |x: ArrayView2<f64>, aff: &Array2<f64>| {
+            let mut layer =
+                BatchNormalizationLayer::new(aff, Array1::zeros(3), Array1::ones(3), 0.9);
+            (layer.forward(&x.to_owned()) * &dout).sum()
+        };
+
+        let mut layer = BatchNormalizationLayer::new(&aff, Array1::zeros(3), Array1::ones(3), 0.9);
+        layer.forward(&x);
+        let dx = layer.backward(&dout);
+
+        let dx_numerical = numerical_gradient(&|x| loss(x, &aff), x.view());
+        let daff_numerical = numerical_gradient(&|aff| loss(x.view(), &aff.to_owned()), aff.view());
+        for (n, a) in dx_numerical.iter().zip(dx.iter()) {
+            assert!((n - a).abs() < 1e-6, "numerical: {}, analytic: {}", n, a);
+        }
+        for (n, a) in daff_numerical.iter().zip(layer.daff.iter()) {
+            assert!((n - a).abs() < 1e-6, "numerical: {}, analytic: {}", n, a);
+        }
     }
 }
@@ -0,0 +1,176 @@
+use crate::layer::layer::Layer;
+use crate::subfunction::{
+    col2im::col2im,
+    im2col::{im2col, output_size},
+};
+use ndarray::{Array1, Array2, Array4, Axis};
+
+pub struct Conv2dLayer<'a> {
+    w: &'a Array4<f64>, // (フィルタ数, チャンネル数, フィルタの高さ, フィルタの幅)
+    b: &'a Array1<f64>, // (フィルタ数)
+    stride: usize,
+    pad: usize,
+    x_shape: (usize, usize, usize, usize),
+    col: Array2<f64>,
+    col_w: Array2<f64>,
+    pub dw: Array4<f64>,
+    pub db: Array1<f64>,
+}
+
+impl<'a> Conv2dLayer<'a> {
+    /// strideが0の場合や、フィルタ数とバイアスの数が合わない場合はpanicする
+    pub fn new(w: &'a Array4<f64>, b: &'a Array1<f64>, stride: usize, pad: usize) -> Self {
+        assert!(stride > 0, "stride must be positive");
+        assert_eq!(
+            w.shape()[0],
+            b.len(),
+            "number of filters and biases must be equal"
+        );
+        Conv2dLayer {
+            w,
+            b,
+            stride,
+            pad,
+            x_shape: (0, 0, 0, 0),
+            col: Array2::zeros((0, 0)),
+            col_w: Array2::zeros((0, 0)),
+            dw: Array4::zeros((0, 0, 0, 0)),
+            db: Array1::zeros(0),
+        }
+    }
+}
+
+impl<'a> Layer<Array4<f64>, Array4<f64>> for Conv2dLayer<'a> {
+    fn forward(&mut self, x: &Array4<f64>) -> Array4<f64> {
+        let (filter_num, c, filter_h, filter_w) = self.w.dim();
+        let (n, x_c, h, w) = x.dim();
+        assert_eq!(x_c, c, "number of input channels must match the filter");
+        let out_h = output_size(h, filter_h, self.stride, self.pad);
+        let out_w = output_size(w, filter_w, self.stride, self.pad);
+
+        self.x_shape = x.dim();
+        self.col = im2col(x.view(), filter_h, filter_w, self.stride, self.pad);
+        self.col_w = self
+            .w
+            .view()
+            .into_shape((filter_num, c * filter_h * filter_w))
+            .unwrap()
+            .t()
+            .to_owned();
+        // (N * out_h * out_w, フィルタ数)の結果を(N, フィルタ数, out_h, out_w)に並べ替える
+        let out = self.col.dot(&self.col_w) + self.b;
+        out.into_shape((n, out_h, out_w, filter_num))
+            .unwrap()
+            .permuted_axes([0, 3, 1, 2])
+            .as_standard_layout()
+            .into_owned()
+    }
+    fn backward(&mut self, dout: &Array4<f64>) -> Array4<f64> {
+        let (filter_num, c, filter_h, filter_w) = self.w.dim();
+        let (n, _, out_h, out_w) = dout.dim();
+        let dout = dout
+            .view()
+            .permuted_axes([0, 2, 3, 1])
+            .as_standard_layout()
+            .into_owned()
+            .into_shape((n * out_h * out_w, filter_num))
+            .unwrap();
+
+        self.db = dout.sum_axis(Axis(0));
+        self.dw = self
+            .col
+            .t()
+            .dot(&dout)
+            .reversed_axes()
+            .as_standard_layout()
+            .into_owned()
+            .into_shape((filter_num, c, filter_h, filter_w))
+            .unwrap();
+
+        let dcol = dout.dot(&self.col_w.t());
+        col2im(
+            dcol.view(),
+            self.x_shape,
+            filter_h,
+            filter_w,
+            self.stride,
+            self.pad,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subfunction::numerical_gradient::numerical_gradient;
+    use ndarray::{Array, ArrayView4, Dimension};
+    use ndarray_rand::rand::{rngs::StdRng, SeedableRng};
+    use ndarray_rand::rand_distr::Normal;
+    use ndarray_rand::RandomExt;
+
+    fn assert_close<D: Dimension>(numerical: &Array<f64, D>, analytic: &Array<f64, D>) {
+        assert_eq!(numerical.shape(), analytic.shape());
+        for (n, a) in numerical.iter().zip(analytic.iter()) {
+            assert!((n - a).abs() < 1e-6, "numerical: {}, analytic: {}", n, a);
+        }
+    }
+
+    #[test]
+    fn forward_matches_direct_convolution() {
+        let x = Array::range(0.0, 16.0, 1.0)
+            .into_shape((1, 1, 4, 4))
+            .unwrap();
+        let w = Array4::ones((1, 1, 2, 2));
+        let b = Array1::from_elem(1, 0.5);
+        let y = Conv2dLayer::new(&w, &b, 2, 0).forward(&x);
+        // 2x2の各領域の和にバイアスを足したもの
+        assert_eq!(y.shape(), &[1, 1, 2, 2]);
+        assert_eq!(
+            y.iter().copied().collect::<Vec<_>>(),
+            vec![10.5, 18.5, 42.5, 50.5]
+        );
+    }
+
+    #[test]
+    fn backward_matches_numerical_gradient() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let dist = Normal::new(0.0, 1.0).unwrap();
+        let x = Array4::random_using((2, 2, 5, 5), dist, &mut rng);
+        let w = Array4::random_using((3, 2, 3, 3), dist, &mut rng);
+        let b = Array1::random_using(3, dist, &mut rng);
+        // 出力に重みを掛けて和を取ったものを損失とする
+        let (stride, pad) = (2, 1);
+        let dout = Array4::random_using((2, 3, 3, 3), dist, &mut rng);
+        let loss = |x: ArrayView4<f64>, w: &Array4<f64>, b: &Array1<f64>| {
+            (Conv2dLayer::new(w, b, stride, pad).forward(&x.to_owned()) * &dout).sum()
+        };
+
+        let mut layer = Conv2dLayer::new(&w, &b, stride, pad);
+        layer.forward(&x);
+        let dx = layer.backward(&dout);
+
+        assert_close(&numerical_gradient(&|x| loss(x, &w, &b), x.view()), &dx);
+        assert_close(
+            &numerical_gradient(&|w| loss(x.view(), &w.to_owned(), &b), w.view()),
+            &layer.dw,
+        );
+        assert_close(
+            &numerical_gradient(&|b| loss(x.view(), &w, &b.to_owned()), b.view()),
+            &layer.db,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "stride must be positive")]
+    fn zero_stride_is_rejected() {
+        let (w, b) = (Array4::ones((1, 1, 2, 2)), Array1::zeros(1));
+        Conv2dLayer::new(&w, &b, 0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "filter size 5 is larger than the padded input size 4")]
+    fn filter_larger_than_input_is_rejected() {
+        let (w, b) = (Array4::ones((1, 1, 5, 5)), Array1::zeros(1));
+        Conv2dLayer::new(&w, &b, 1, 1).forward(&Array4::zeros((1, 1, 2, 2)));
+    }
+}
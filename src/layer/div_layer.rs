@@ -13,6 +13,12 @@ impl<Dim: Dimension> DivLayer<Dim> {
     }
 }
 
+impl<Dim: Dimension> Default for DivLayer<Dim> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for DivLayer<Dim> {
     fn forward(&mut self, x: &Array<f64, Dim>) -> Array<f64, Dim> {
         self.x = x.clone();
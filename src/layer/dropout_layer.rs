@@ -0,0 +1,105 @@
+use crate::layer::{layer::Layer, sequential_layer::SequentialLayer};
+use ndarray::{prelude::Array, Dimension, Ix2};
+use ndarray_rand::{
+    rand::{rngs::StdRng, SeedableRng},
+    rand_distr::Uniform,
+    RandomExt,
+};
+
+pub struct DropoutLayer<Dim: Dimension> {
+    dropout_ratio: f64,
+    train_flg: bool,
+    mask: Array<f64, Dim>,
+    rng: StdRng,
+}
+
+impl<Dim: Dimension> DropoutLayer<Dim> {
+    pub fn new(dropout_ratio: f64) -> Self {
+        Self::from_rng(dropout_ratio, StdRng::from_entropy())
+    }
+    /// 乱数のシードを固定し、消去するニューロンの選び方を再現できるようにする
+    pub fn with_seed(dropout_ratio: f64, seed: u64) -> Self {
+        Self::from_rng(dropout_ratio, StdRng::seed_from_u64(seed))
+    }
+    fn from_rng(dropout_ratio: f64, rng: StdRng) -> Self {
+        // 1以上では残したニューロンの出力を無限大倍することになる
+        assert!(
+            (0.0..1.0).contains(&dropout_ratio),
+            "dropout_ratio must be in [0, 1)"
+        );
+        DropoutLayer {
+            dropout_ratio,
+            train_flg: true,
+            mask: Array::zeros(Dim::default()),
+            rng,
+        }
+    }
+    /// 学習時はニューロンをランダムに消去し、推論時は入力をそのまま出力する
+    pub fn set_train_flg(&mut self, train_flg: bool) {
+        self.train_flg = train_flg;
+    }
+}
+
+impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for DropoutLayer<Dim> {
+    fn forward(&mut self, x: &Array<f64, Dim>) -> Array<f64, Dim> {
+        if !self.train_flg {
+            return x.clone();
+        }
+        // 残したニューロンの出力を1 / (1 - dropout_ratio)倍し、推論時にスケールを合わせなくて済むようにする
+        let scale = 1.0 / (1.0 - self.dropout_ratio);
+        self.mask = Array::random_using(x.raw_dim(), Uniform::new(0.0, 1.0), &mut self.rng)
+            .mapv(|r| if r > self.dropout_ratio { scale } else { 0.0 });
+        x * &self.mask
+    }
+    fn backward(&mut self, dout: &Array<f64, Dim>) -> Array<f64, Dim> {
+        if !self.train_flg {
+            return dout.clone();
+        }
+        dout * &self.mask
+    }
+}
+
+impl SequentialLayer for DropoutLayer<Ix2> {
+    fn set_train_flg(&mut self, train_flg: bool) {
+        self.train_flg = train_flg;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    #[test]
+    fn same_seed_drops_same_neurons() {
+        let x = Array2::ones((4, 50));
+        let y1 = DropoutLayer::with_seed(0.5, 7).forward(&x);
+        let y2 = DropoutLayer::with_seed(0.5, 7).forward(&x);
+        assert_eq!(y1, y2);
+        // 残したニューロンは2倍され、期待値が変わらない
+        assert!(y1.iter().all(|&v| v == 0.0 || v == 2.0));
+        let mean = y1.mean().unwrap();
+        assert!((mean - 1.0).abs() < 0.2, "mean: {}", mean);
+    }
+
+    #[test]
+    fn backward_uses_forward_mask() {
+        let mut layer = DropoutLayer::with_seed(0.3, 1);
+        let y = layer.forward(&Array2::ones((3, 10)));
+        assert_eq!(layer.backward(&Array2::ones((3, 10))), y);
+    }
+
+    #[test]
+    fn inference_passes_input_through() {
+        let mut layer = DropoutLayer::with_seed(0.5, 0);
+        layer.set_train_flg(false);
+        let x = Array2::from_elem((2, 3), 1.5);
+        assert_eq!(layer.forward(&x), x);
+    }
+
+    #[test]
+    #[should_panic(expected = "dropout_ratio must be in [0, 1)")]
+    fn ratio_of_one_is_rejected() {
+        DropoutLayer::<Ix2>::new(1.0);
+    }
+}
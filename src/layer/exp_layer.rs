@@ -13,6 +13,12 @@ impl<Dim: Dimension> ExpLayer<Dim> {
     }
 }
 
+impl<Dim: Dimension> Default for ExpLayer<Dim> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for ExpLayer<Dim> {
     fn forward(&mut self, x: &Array<f64, Dim>) -> Array<f64, Dim> {
         self.out = x.map(|&x| x.exp());
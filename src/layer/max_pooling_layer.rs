@@ -0,0 +1,123 @@
+use crate::layer::layer::Layer;
+use crate::subfunction::{
+    argmax::argmax,
+    col2im::col2im,
+    im2col::{im2col, output_size},
+};
+use ndarray::{Array1, Array2, Array4};
+
+pub struct MaxPoolingLayer {
+    pool_h: usize,
+    pool_w: usize,
+    stride: usize,
+    pad: usize,
+    x_shape: (usize, usize, usize, usize),
+    arg_max: Array1<usize>,
+}
+
+impl MaxPoolingLayer {
+    /// プーリングの幅や高さ、strideが0の場合はpanicする
+    pub fn new(pool_h: usize, pool_w: usize, stride: usize, pad: usize) -> Self {
+        assert!(pool_h > 0 && pool_w > 0, "pool size must be positive");
+        assert!(stride > 0, "stride must be positive");
+        MaxPoolingLayer {
+            pool_h,
+            pool_w,
+            stride,
+            pad,
+            x_shape: (0, 0, 0, 0),
+            arg_max: Array1::zeros(0),
+        }
+    }
+}
+
+impl Layer<Array4<f64>, Array4<f64>> for MaxPoolingLayer {
+    fn forward(&mut self, x: &Array4<f64>) -> Array4<f64> {
+        let (n, c, h, w) = x.dim();
+        let out_h = output_size(h, self.pool_h, self.stride, self.pad);
+        let out_w = output_size(w, self.pool_w, self.stride, self.pad);
+
+        self.x_shape = x.dim();
+        // 1行が1チャンネル分のプーリング領域になるように並べる
+        let col = im2col(x.view(), self.pool_h, self.pool_w, self.stride, self.pad)
+            .into_shape((n * out_h * out_w * c, self.pool_h * self.pool_w))
+            .unwrap();
+        self.arg_max = col.outer_iter().map(argmax).collect();
+        let out = self
+            .arg_max
+            .indexed_iter()
+            .map(|(i, &j)| col[[i, j]])
+            .collect::<Array1<f64>>();
+        out.into_shape((n, out_h, out_w, c))
+            .unwrap()
+            .permuted_axes([0, 3, 1, 2])
+            .as_standard_layout()
+            .into_owned()
+    }
+    fn backward(&mut self, dout: &Array4<f64>) -> Array4<f64> {
+        let (n, c, out_h, out_w) = dout.dim();
+        let pool_size = self.pool_h * self.pool_w;
+        let dout = dout
+            .view()
+            .permuted_axes([0, 2, 3, 1])
+            .as_standard_layout()
+            .into_owned()
+            .into_shape(n * out_h * out_w * c)
+            .unwrap();
+
+        // 勾配は順伝播で最大値をとった位置にのみ流す
+        let mut dmax = Array2::zeros((dout.len(), pool_size));
+        for (i, &j) in self.arg_max.indexed_iter() {
+            dmax[[i, j]] = dout[i];
+        }
+        let dcol = dmax.into_shape((n * out_h * out_w, c * pool_size)).unwrap();
+        col2im(
+            dcol.view(),
+            self.x_shape,
+            self.pool_h,
+            self.pool_w,
+            self.stride,
+            self.pad,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr2, Array, Axis};
+
+    #[test]
+    fn gradient_flows_only_to_maximum() {
+        let x = Array::range(0.0, 16.0, 1.0)
+            .into_shape((1, 1, 4, 4))
+            .unwrap();
+        let mut layer = MaxPoolingLayer::new(2, 2, 2, 0);
+        let y = layer.forward(&x);
+        assert_eq!(
+            y.index_axis(Axis(0), 0).index_axis(Axis(0), 0),
+            arr2(&[[5.0, 7.0], [13.0, 15.0]])
+        );
+        let dx = layer.backward(&Array4::ones((1, 1, 2, 2)));
+        let expected = x.mapv(|v| {
+            if [5.0, 7.0, 13.0, 15.0].contains(&v) {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        assert_eq!(dx, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "stride must be positive")]
+    fn zero_stride_is_rejected() {
+        MaxPoolingLayer::new(2, 2, 0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "pool size must be positive")]
+    fn zero_pool_size_is_rejected() {
+        MaxPoolingLayer::new(0, 2, 1, 0);
+    }
+}
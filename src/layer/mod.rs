@@ -1,10 +1,17 @@
 pub mod add_layer;
 pub mod affine_layer;
+pub mod average_pooling_layer;
 pub mod batch_normalization_layer;
+pub mod conv2d_layer;
 pub mod div_layer;
+pub mod dropout_layer;
 pub mod exp_layer;
+#[allow(clippy::module_inception)]
 pub mod layer;
+pub mod max_pooling_layer;
 pub mod mul_layer;
 pub mod relu_layer;
+pub mod sequential_layer;
 pub mod sigmoid_layer;
 pub mod softmax_with_loss_layer;
+pub mod sparse_softmax_with_loss_layer;
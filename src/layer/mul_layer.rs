@@ -15,6 +15,12 @@ impl<Dim: Dimension> MulLayer<Dim> {
     }
 }
 
+impl<Dim: Dimension> Default for MulLayer<Dim> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dim: Dimension> Layer<(Array<f64, Dim>, Array<f64, Dim>), Array<f64, Dim>> for MulLayer<Dim> {
     fn forward(&mut self, (x, y): &(Array<f64, Dim>, Array<f64, Dim>)) -> Array<f64, Dim> {
         self.x = x.clone();
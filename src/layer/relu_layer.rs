@@ -1,5 +1,5 @@
-use crate::layer::layer::Layer;
-use ndarray::{prelude::Array, Dimension};
+use crate::layer::{layer::Layer, sequential_layer::SequentialLayer};
+use ndarray::{prelude::Array, Dimension, Ix2};
 
 pub struct ReluLayer<Dim: Dimension> {
     mask: Array<f64, Dim>,
@@ -13,6 +13,12 @@ impl<Dim: Dimension> ReluLayer<Dim> {
     }
 }
 
+impl<Dim: Dimension> Default for ReluLayer<Dim> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for ReluLayer<Dim> {
     fn forward(&mut self, x: &Array<f64, Dim>) -> Array<f64, Dim> {
         self.mask = x.map(|&x| if x > 0.0 { 1.0 } else { 0.0 });
@@ -22,3 +28,5 @@ impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for ReluLayer<Dim>
         dout * &self.mask
     }
 }
+
+impl SequentialLayer for ReluLayer<Ix2> {}
@@ -0,0 +1,16 @@
+use crate::{layer::layer::Layer, model::Parameter};
+use ndarray::{Array2, ArrayViewMutD};
+
+/// Sequentialに積み重ねることのできる層
+pub trait SequentialLayer: Layer<Array2<f64>, Array2<f64>> {
+    /// 層が持つパラメータを、直前のbackwardで求めた勾配と共に返す
+    fn parameters(&mut self) -> Vec<Parameter<'_>> {
+        Vec::new()
+    }
+    /// 勾配を持たない状態を返す
+    fn buffers(&mut self) -> Vec<(String, ArrayViewMutD<'_, f64>)> {
+        Vec::new()
+    }
+    /// 学習時と推論時で振る舞いが変わる層は、これを上書きする
+    fn set_train_flg(&mut self, _train_flg: bool) {}
+}
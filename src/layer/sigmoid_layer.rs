@@ -1,5 +1,5 @@
-use crate::layer::layer::Layer;
-use ndarray::{prelude::Array, Dimension};
+use crate::layer::{layer::Layer, sequential_layer::SequentialLayer};
+use ndarray::{prelude::Array, Dimension, Ix2};
 
 pub struct SigmoidLayer<Dim: Dimension> {
     out: Array<f64, Dim>,
@@ -13,6 +13,12 @@ impl<Dim: Dimension> SigmoidLayer<Dim> {
     }
 }
 
+impl<Dim: Dimension> Default for SigmoidLayer<Dim> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for SigmoidLayer<Dim> {
     fn forward(&mut self, x: &Array<f64, Dim>) -> Array<f64, Dim> {
         self.out = x.map(|&x| 1.0 / (1.0 + (-x).exp()));
@@ -22,3 +28,5 @@ impl<Dim: Dimension> Layer<Array<f64, Dim>, Array<f64, Dim>> for SigmoidLayer<Di
         dout * &self.out * (1.0 - &self.out)
     }
 }
+
+impl SequentialLayer for SigmoidLayer<Ix2> {}
@@ -0,0 +1,77 @@
+use crate::layer::layer::Layer;
+use crate::subfunction::{
+    softmax_batch::softmax_batch, sparse_cross_entropy_error::sparse_cross_entropy_error,
+};
+use ndarray::prelude::{Array1, Array2};
+
+/// 正解ラベルをクラスのインデックスで受け取るSoftmaxWithLossLayer
+pub struct SparseSoftmaxWithLossLayer {
+    loss: f64,
+    y: Array2<f64>,
+    t: Array1<usize>,
+}
+
+impl SparseSoftmaxWithLossLayer {
+    pub fn new(t: &Array1<usize>) -> Self {
+        SparseSoftmaxWithLossLayer {
+            loss: 0.0,
+            y: Array2::zeros((0, 0)),
+            t: t.clone(),
+        }
+    }
+}
+
+impl Layer<Array2<f64>, f64> for SparseSoftmaxWithLossLayer {
+    fn forward(&mut self, y: &Array2<f64>) -> f64 {
+        self.y = softmax_batch(y.view());
+        self.loss = sparse_cross_entropy_error(self.y.view(), self.t.view());
+        self.loss
+    }
+    fn backward(&mut self, _: &f64) -> Array2<f64> {
+        let batch_size = self.t.len() as f64;
+        let mut dx = self.y.clone();
+        for (i, &t) in self.t.iter().enumerate() {
+            dx[[i, t]] -= 1.0;
+        }
+        dx / batch_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::softmax_with_loss_layer::SoftmaxWithLossLayer;
+    use crate::subfunction::one_hot::one_hot;
+    use ndarray::{arr1, arr2};
+
+    #[test]
+    fn matches_one_hot_loss_and_gradient() {
+        let y = arr2(&[
+            [0.3, -1.2, 2.0],
+            [1.5, 0.1, -0.4],
+            [0.0, 0.0, 0.0],
+            [-2.0, 3.0, 0.5],
+        ]);
+        let t = arr1(&[2, 0, 1, 1]);
+        let mut sparse = SparseSoftmaxWithLossLayer::new(&t);
+        let one_hot = one_hot(&t, 3);
+        let mut dense = SoftmaxWithLossLayer::new(&one_hot);
+
+        let loss = sparse.forward(&y);
+        assert!((loss - dense.forward(&y)).abs() < 1e-12);
+        let dx = sparse.backward(&1.0);
+        for (a, b) in dx.iter().zip(dense.backward(&1.0).iter()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        // 各行の勾配は(softmaxの出力 - one-hot) / バッチサイズなので、行の和は0になる
+        for row in dx.rows() {
+            assert!(row.sum().abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "batch size of y and t must match")]
+    fn mismatched_batch_size_is_rejected() {
+        SparseSoftmaxWithLossLayer::new(&arr1(&[0])).forward(&Array2::zeros((2, 3)));
+    }
+}
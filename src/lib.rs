@@ -0,0 +1,17 @@
+pub mod checkpoint;
+pub mod data_loader;
+pub mod hyperparameter_search;
+pub mod labels;
+pub mod layer;
+pub mod lr_scheduler;
+pub mod mnist;
+pub mod model;
+pub mod numpy;
+pub mod optimize;
+pub mod plot;
+pub mod regularization;
+pub mod safetensors;
+pub mod sequential;
+pub mod subfunction;
+pub mod trainer;
+pub mod two_layer_net;
@@ -0,0 +1,8 @@
+pub mod constant_lr;
+pub mod cosine_annealing_warm_restarts;
+pub mod exponential_decay;
+pub mod linear_warmup;
+#[allow(clippy::module_inception)]
+pub mod lr_scheduler;
+pub mod reduce_lr_on_plateau;
+pub mod step_decay;
@@ -0,0 +1,32 @@
+use super::lr_scheduler::LrScheduler;
+
+pub struct ConstantLr {
+    learning_rate: f64,
+}
+
+impl ConstantLr {
+    pub fn new(learning_rate: f64) -> Self {
+        Self { learning_rate }
+    }
+}
+
+impl LrScheduler for ConstantLr {
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn step(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_learning_rate_across_steps() {
+        let mut scheduler = ConstantLr::new(0.01);
+        for _ in 0..3 {
+            scheduler.step();
+        }
+        assert_eq!(scheduler.learning_rate(), 0.01);
+    }
+}
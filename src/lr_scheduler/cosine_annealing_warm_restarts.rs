@@ -0,0 +1,119 @@
+use std::f64::consts::PI;
+
+use ndarray::{arr0, ArrayD};
+
+use super::lr_scheduler::LrScheduler;
+use crate::checkpoint::{state_scalar, CheckpointError};
+
+/// 学習率を`base_learning_rate`から`min_learning_rate`までコサイン曲線に沿って下げ、
+/// 周期の終わりで`base_learning_rate`に戻す。周期は`period`から始まり、戻すたびに`period_mult`倍になる
+///
+/// `period`や`period_mult`が0の場合は学習率がNaNになるため、newでpanicする
+pub struct CosineAnnealingWarmRestarts {
+    base_learning_rate: f64,
+    min_learning_rate: f64,
+    period: usize,
+    period_mult: usize,
+    iter_in_period: usize,
+}
+
+impl CosineAnnealingWarmRestarts {
+    pub fn new(
+        base_learning_rate: f64,
+        min_learning_rate: f64,
+        period: usize,
+        period_mult: usize,
+    ) -> Self {
+        assert!(period > 0, "period must be positive");
+        assert!(period_mult > 0, "period_mult must be positive");
+        Self {
+            base_learning_rate,
+            min_learning_rate,
+            period,
+            period_mult,
+            iter_in_period: 0,
+        }
+    }
+}
+
+impl LrScheduler for CosineAnnealingWarmRestarts {
+    fn learning_rate(&self) -> f64 {
+        let progress = self.iter_in_period as f64 / self.period as f64;
+        self.min_learning_rate
+            + (self.base_learning_rate - self.min_learning_rate) * (1.0 + (PI * progress).cos())
+                / 2.0
+    }
+    fn step(&mut self) {
+        self.iter_in_period += 1;
+        if self.iter_in_period >= self.period {
+            self.iter_in_period = 0;
+            self.period *= self.period_mult;
+        }
+    }
+    /// 周期は戻すたびに伸びるため、周期内の位置と共に現在の周期も保存する
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        vec![
+            (
+                "iter_in_period".to_string(),
+                arr0(self.iter_in_period as f64).into_dyn(),
+            ),
+            ("period".to_string(), arr0(self.period as f64).into_dyn()),
+        ]
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        let iter_in_period = state_scalar(state, "iter_in_period")? as usize;
+        let period = state_scalar(state, "period")? as usize;
+        if period == 0 || iter_in_period >= period {
+            return Err(CheckpointError::InvalidData(format!(
+                "iteration {} is outside the period {}",
+                iter_in_period, period
+            )));
+        }
+        (self.iter_in_period, self.period) = (iter_in_period, period);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learning_rates(scheduler: &mut impl LrScheduler, steps: usize) -> Vec<f64> {
+        (0..steps)
+            .map(|_| {
+                let learning_rate = scheduler.learning_rate();
+                scheduler.step();
+                learning_rate
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restarts_with_growing_period() {
+        let mut scheduler = CosineAnnealingWarmRestarts::new(1.0, 0.0, 2, 2);
+        let expected = [
+            1.0,
+            0.5,
+            1.0,
+            0.8535533905932737,
+            0.5,
+            0.14644660940672627,
+            1.0,
+        ];
+        for (lr, expected) in learning_rates(&mut scheduler, 7).iter().zip(expected) {
+            assert!((lr - expected).abs() < 1e-12, "{} != {}", lr, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "period must be positive")]
+    fn zero_period_is_rejected() {
+        CosineAnnealingWarmRestarts::new(1.0, 0.0, 0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "period_mult must be positive")]
+    fn zero_period_mult_is_rejected() {
+        CosineAnnealingWarmRestarts::new(1.0, 0.0, 2, 0);
+    }
+}
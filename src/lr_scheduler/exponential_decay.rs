@@ -0,0 +1,55 @@
+use ndarray::{arr0, ArrayD};
+
+use super::lr_scheduler::LrScheduler;
+use crate::checkpoint::{state_scalar, CheckpointError};
+
+/// 1ステップごとに学習率を`gamma`倍する
+pub struct ExponentialDecay {
+    base_learning_rate: f64,
+    gamma: f64,
+    iter: usize,
+}
+
+impl ExponentialDecay {
+    pub fn new(base_learning_rate: f64, gamma: f64) -> Self {
+        Self {
+            base_learning_rate,
+            gamma,
+            iter: 0,
+        }
+    }
+}
+
+impl LrScheduler for ExponentialDecay {
+    fn learning_rate(&self) -> f64 {
+        self.base_learning_rate * self.gamma.powi(self.iter as i32)
+    }
+    fn step(&mut self) {
+        self.iter += 1;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        vec![("iter".to_string(), arr0(self.iter as f64).into_dyn())]
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        self.iter = state_scalar(state, "iter")? as usize;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplies_by_gamma_every_step() {
+        let mut scheduler = ExponentialDecay::new(1.0, 0.5);
+        let learning_rates = (0..4)
+            .map(|_| {
+                let learning_rate = scheduler.learning_rate();
+                scheduler.step();
+                learning_rate
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(learning_rates, vec![1.0, 0.5, 0.25, 0.125]);
+    }
+}
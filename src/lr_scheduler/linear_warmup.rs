@@ -0,0 +1,85 @@
+use ndarray::{arr0, ArrayD};
+
+use super::lr_scheduler::LrScheduler;
+use crate::checkpoint::{state_scalar, CheckpointError};
+
+/// 最初の`warmup_steps`ステップの間、学習率を0から`after`の学習率まで線形に上げ、その後は`after`に従う
+pub struct LinearWarmup<S: LrScheduler> {
+    warmup_steps: usize,
+    iter: usize,
+    after: S,
+}
+
+impl<S: LrScheduler> LinearWarmup<S> {
+    pub fn new(warmup_steps: usize, after: S) -> Self {
+        Self {
+            warmup_steps,
+            iter: 0,
+            after,
+        }
+    }
+}
+
+impl<S: LrScheduler> LrScheduler for LinearWarmup<S> {
+    fn learning_rate(&self) -> f64 {
+        if self.iter < self.warmup_steps {
+            self.after.learning_rate() * (self.iter + 1) as f64 / self.warmup_steps as f64
+        } else {
+            self.after.learning_rate()
+        }
+    }
+    fn step(&mut self) {
+        if self.iter < self.warmup_steps {
+            self.iter += 1;
+        } else {
+            self.after.step();
+        }
+    }
+    fn report_metric(&mut self, metric: f64) {
+        self.after.report_metric(metric);
+    }
+    /// ウォームアップ後のスケジューラの状態は、名前に"after."を付けて含める
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        std::iter::once(("iter".to_string(), arr0(self.iter as f64).into_dyn()))
+            .chain(
+                self.after
+                    .state()
+                    .into_iter()
+                    .map(|(name, value)| (format!("after.{}", name), value)),
+            )
+            .collect()
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        let iter = state_scalar(state, "iter")? as usize;
+        let after = state
+            .iter()
+            .filter_map(|(name, value)| {
+                name.strip_prefix("after.")
+                    .map(|name| (name.to_string(), value.clone()))
+            })
+            .collect::<Vec<_>>();
+        self.after.load_state(&after)?;
+        self.iter = iter;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_scheduler::exponential_decay::ExponentialDecay;
+
+    #[test]
+    fn warms_up_then_follows_inner_schedule() {
+        let mut scheduler = LinearWarmup::new(4, ExponentialDecay::new(1.0, 0.5));
+        let learning_rates = (0..6)
+            .map(|_| {
+                let learning_rate = scheduler.learning_rate();
+                scheduler.step();
+                learning_rate
+            })
+            .collect::<Vec<_>>();
+        // ウォームアップ中は内側のスケジューラを進めない
+        assert_eq!(learning_rates, vec![0.25, 0.5, 0.75, 1.0, 1.0, 0.5]);
+    }
+}
@@ -0,0 +1,21 @@
+use ndarray::ArrayD;
+
+use crate::checkpoint::CheckpointError;
+
+/// 学習の進み具合に応じて学習率を決める
+pub trait LrScheduler {
+    /// 現在の学習率
+    fn learning_rate(&self) -> f64;
+    /// 1ステップ進める。何をステップとするか(イテレーション、エポック)は呼び出し側が決める
+    fn step(&mut self);
+    /// 検証データでの損失など、小さいほど良い指標を報告する。指標を用いないスケジューラでは何もしない
+    fn report_metric(&mut self, _metric: f64) {}
+    /// チェックポイントに保存する、学習の進み具合を表す状態。設定値は含めない
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        Vec::new()
+    }
+    /// stateで得た状態を復元する。エラーの場合は状態を変更しない
+    fn load_state(&mut self, _state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        Ok(())
+    }
+}
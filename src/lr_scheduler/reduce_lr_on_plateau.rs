@@ -0,0 +1,95 @@
+use ndarray::{arr0, ArrayD};
+
+use super::lr_scheduler::LrScheduler;
+use crate::checkpoint::{state_scalar, CheckpointError};
+
+/// 報告された指標が`patience`回続けて`min_delta`以上改善しなかったとき、学習率を`factor`倍する
+pub struct ReduceLrOnPlateau {
+    learning_rate: f64,
+    factor: f64,
+    patience: usize,
+    min_delta: f64,
+    min_learning_rate: f64,
+    best: f64,
+    num_bad_reports: usize,
+}
+
+impl ReduceLrOnPlateau {
+    pub fn new(
+        learning_rate: f64,
+        factor: f64,
+        patience: usize,
+        min_delta: f64,
+        min_learning_rate: f64,
+    ) -> Self {
+        Self {
+            learning_rate,
+            factor,
+            patience,
+            min_delta,
+            min_learning_rate,
+            best: f64::INFINITY,
+            num_bad_reports: 0,
+        }
+    }
+}
+
+impl LrScheduler for ReduceLrOnPlateau {
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn step(&mut self) {}
+    fn report_metric(&mut self, metric: f64) {
+        if metric < self.best - self.min_delta {
+            self.best = metric;
+            self.num_bad_reports = 0;
+            return;
+        }
+        self.num_bad_reports += 1;
+        if self.num_bad_reports > self.patience {
+            self.learning_rate = (self.learning_rate * self.factor).max(self.min_learning_rate);
+            self.num_bad_reports = 0;
+        }
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        vec![
+            (
+                "learning_rate".to_string(),
+                arr0(self.learning_rate).into_dyn(),
+            ),
+            ("best".to_string(), arr0(self.best).into_dyn()),
+            (
+                "num_bad_reports".to_string(),
+                arr0(self.num_bad_reports as f64).into_dyn(),
+            ),
+        ]
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        let learning_rate = state_scalar(state, "learning_rate")?;
+        let best = state_scalar(state, "best")?;
+        let num_bad_reports = state_scalar(state, "num_bad_reports")? as usize;
+        (self.learning_rate, self.best, self.num_bad_reports) =
+            (learning_rate, best, num_bad_reports);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduces_after_patience_reports_without_improvement() {
+        let mut scheduler = ReduceLrOnPlateau::new(1.0, 0.5, 1, 0.01, 0.2);
+        let mut learning_rates = Vec::new();
+        for metric in [1.0, 0.995, 0.999, 0.9, 0.95, 0.95, 0.95, 0.95, 0.95, 0.95] {
+            scheduler.report_metric(metric);
+            learning_rates.push(scheduler.learning_rate());
+        }
+        // min_delta未満の改善は改善とみなさず、学習率はmin_learning_rateより下げない
+        assert_eq!(
+            learning_rates,
+            vec![1.0, 1.0, 0.5, 0.5, 0.5, 0.25, 0.25, 0.2, 0.2, 0.2]
+        );
+    }
+}
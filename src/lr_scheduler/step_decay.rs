@@ -0,0 +1,64 @@
+use ndarray::{arr0, ArrayD};
+
+use super::lr_scheduler::LrScheduler;
+use crate::checkpoint::{state_scalar, CheckpointError};
+
+/// `step_size`ステップごとに学習率を`gamma`倍する
+pub struct StepDecay {
+    base_learning_rate: f64,
+    step_size: usize,
+    gamma: f64,
+    iter: usize,
+}
+
+impl StepDecay {
+    pub fn new(base_learning_rate: f64, step_size: usize, gamma: f64) -> Self {
+        assert!(step_size > 0, "step_size must be positive");
+        Self {
+            base_learning_rate,
+            step_size,
+            gamma,
+            iter: 0,
+        }
+    }
+}
+
+impl LrScheduler for StepDecay {
+    fn learning_rate(&self) -> f64 {
+        self.base_learning_rate * self.gamma.powi((self.iter / self.step_size) as i32)
+    }
+    fn step(&mut self) {
+        self.iter += 1;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        vec![("iter".to_string(), arr0(self.iter as f64).into_dyn())]
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        self.iter = state_scalar(state, "iter")? as usize;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decays_every_step_size_steps() {
+        let mut scheduler = StepDecay::new(1.0, 2, 0.5);
+        let learning_rates = (0..5)
+            .map(|_| {
+                let learning_rate = scheduler.learning_rate();
+                scheduler.step();
+                learning_rate
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(learning_rates, vec![1.0, 1.0, 0.5, 0.5, 0.25]);
+    }
+
+    #[test]
+    #[should_panic(expected = "step_size must be positive")]
+    fn zero_step_size_is_rejected() {
+        StepDecay::new(1.0, 0, 0.5);
+    }
+}
@@ -1,149 +1,174 @@
-mod layer;
-use layer::affine_layer::AffineLayer;
-use layer::layer::Layer;
-use layer::relu_layer::ReluLayer;
-use layer::softmax_with_loss_layer::SoftmaxWithLossLayer;
-mod mnist;
-mod optimize;
-mod subfunction;
-mod two_layer_net;
-use ndarray::prelude::*;
+use ndarray::{Array2, IxDyn};
 use ndarray_rand::rand;
-use ndarray_rand::rand::seq::IteratorRandom;
 use ndarray_rand::rand::Rng;
 use ndarray_rand::rand_distr::Normal;
-use optimize::optimize::Optimize;
-use two_layer_net::TwoLayerNet;
-use two_layer_net::TwoLayerNetGradient;
-
-use crate::layer::batch_normalization_layer::BatchNormalizationLayer;
-use crate::optimize::sgd::SGD;
+use zero_deeplearning::{
+    checkpoint::Checkpoint,
+    data_loader::DataLoader,
+    hyperparameter_search::{
+        search::{HyperparameterSearch, Strategy},
+        search_space::SearchSpace,
+        successive_halving::SuccessiveHalving,
+    },
+    labels::Labels,
+    mnist::{self, mnist_paths::MnistPaths, sampling::Sampling},
+    optimize::{gradient_clipping::GradientClipping, optimizer::Optimizer, sgd::SGD},
+    plot::training_curves::save_training_curves,
+    regularization::{Penalty, Regularization},
+    trainer::{
+        early_stopping::{EarlyStopping, Monitor},
+        history::Evaluation,
+        trainer::{Trainer, ValidationSchedule},
+    },
+    two_layer_net::TwoLayerNet,
+};
 fn separator() -> String {
     (0..20).map(|_| "-").collect::<String>()
 }
 
-fn main() {
-    let training_size = 50_000;
-    let validation_size = 10_000;
+/// 2層ニューラルネットワークをSGDで学習するTrainerを作る
+fn create_trainer(
+    learning_rate: f64,
+    weight_decay: f64,
+    loader: DataLoader<Array2<f64>>,
+) -> Trainer<TwoLayerNet, SGD<IxDyn>, Array2<f64>> {
     let input_layer_size = 28 * 28;
     let hidden_layer_size = 50;
     let output_layer_size = 10;
-    let (x_train, t_train, x_val, t_val, x_test, t_test) =
-        mnist::load_mnist::load_mnist(Some(training_size), Some(validation_size), Some(10_000));
+    let network = TwoLayerNet::new(
+        input_layer_size,
+        hidden_layer_size,
+        output_layer_size,
+        &Normal::new(0.0, 1.0 / (input_layer_size as f64)).unwrap(),
+    );
+    // 重み減衰は勾配に加えずオプティマイザで直接行う。バイアスとバッチ正規化のパラメータには行わない
+    let optimizer = Optimizer::new(move || SGD::new(learning_rate))
+        .with_gradient_clipping(GradientClipping::Norm(5.0))
+        .with_weight_decay(Regularization::new(Penalty::L2(weight_decay)).exclude(&[
+            "b1",
+            "b2",
+            "batch_aff",
+        ]));
+    Trainer::new(network, optimizer, Labels::loss_layer, loader)
+}
+
+fn main() {
+    let training_size = 50_000;
+    let validation_size = 10_000;
+    let (x_train, t_train, x_val, t_val, x_test, t_test) = match mnist::load_mnist::load_mnist(
+        &MnistPaths::default(),
+        Some(training_size),
+        Some(validation_size),
+        Some(10_000),
+        Sampling::First,
+    ) {
+        Ok(dataset) => dataset,
+        Err(e) => {
+            eprintln!("failed to load MNIST: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let batch_size = 100;
-    let iters_num = 10_000;
-    let iters_num_per_val = 500;
+    // 1エポックは500イテレーション。本学習は早期終了しなければepochsエポック行う
+    let epochs = 20;
 
-    let all_indexes = (0..(training_size as usize)).collect::<Vec<usize>>();
-    let mut rng = rand::thread_rng();
-    // 各イテレートで用いる学習データのインデックスを固定化する
-    let indexes = (0..iters_num_per_val)
-        .map(|_| {
-            all_indexes
-                .iter()
-                .choose_multiple(&mut rng, batch_size as usize)
-                .iter()
-                .map(|&i| *i)
-                .collect::<Vec<usize>>()
-        })
-        .collect::<Vec<Vec<usize>>>();
+    let train_loader = DataLoader::new(x_train, t_train, batch_size)
+        .with_drop_last(true)
+        .with_prefetch(4);
     // ハイパーパラメータ
     // - learning_rate
     // - weight_decay
-    // をランダムに選択し学習データで学習。20回繰り返す
-    // (バリデーションデータでのloss, learning_rate, weight_decay)のarray
-    let mut val_results = Vec::<(f64, f64, f64)>::new();
-    for i_val in 0..20 {
-        let learning_rate = (10.0 as f64).powf(rng.gen_range(-6.0..-2.0));
-        let weight_decay = (10.0 as f64).powf(rng.gen_range(-16.0..-8.0));
-        println!(
-            "start iterate: {}\nlearning_rate: {}\nweight_decay: {}",
-            i_val, learning_rate, weight_decay
-        );
-
-        let mut network = TwoLayerNet::new(
-            input_layer_size,
-            hidden_layer_size,
-            output_layer_size,
-            &Normal::new(0.0, 1.0 / (input_layer_size as f64)).unwrap(),
+    // をランダムに20組選び、逐次半減法で探索する。
+    // 1エポック学習して検証データでの損失が上位1/3の組だけを3エポック、さらにその上位1/3を9エポックまで学習する
+    let space = SearchSpace::new()
+        .log_uniform("learning_rate", 1e-6, 1e-2)
+        .log_uniform("weight_decay", 1e-16, 1e-8);
+    let strategy = Strategy::Random {
+        trials: 20,
+        seed: rand::thread_rng().gen(),
+    };
+    let results = HyperparameterSearch::new(space, strategy)
+        .minimize("val_loss")
+        .run_successive_halving(
+            SuccessiveHalving::new(1, 9).with_eta(3),
+            |params| {
+                println!("start trial: {}", params);
+                // 各試行で同じミニバッチを用いるよう、シードを固定する
+                create_trainer(
+                    params.f64("learning_rate"),
+                    params.f64("weight_decay"),
+                    train_loader.clone().with_seed(0),
+                )
+            },
+            |trainer, epochs| {
+                trainer.fit(epochs);
+                // 検証データで評価
+                let Evaluation {
+                    loss: val_loss,
+                    accuracy: val_acc,
+                    ..
+                } = trainer.evaluate(&x_val, &t_val);
+                println!(
+                    "epoch: {}, val_loss: {:?}, val_acc: {:?}",
+                    trainer.history().epoch_loss.len(),
+                    val_loss,
+                    val_acc
+                );
+                println!("{}", separator());
+                val_loss
+            },
         );
-        let mut sgd_w1 = SGD::<Ix2>::new(learning_rate);
-        let mut sgd_b1 = SGD::<Ix1>::new(learning_rate);
-        let mut sgd_w2 = SGD::<Ix2>::new(learning_rate);
-        let mut sgd_b2 = SGD::<Ix1>::new(learning_rate);
-        let mut sgd_batch_aff = SGD::<Ix1>::new(learning_rate);
-
-        // 学習
-        for i in 0..iters_num_per_val {
-            let batch_mask = &indexes[i];
-            let x_batch = x_train.select(Axis(0), &batch_mask);
-            let t_batch = t_train.select(Axis(0), &batch_mask);
-
-            let mut grad = network.gradient(&x_batch, &t_batch);
-            grad.dw1 = weight_decay * &network.w1 + &grad.dw1;
-            grad.dw2 = weight_decay * &network.w2 + &grad.dw2;
-            grad.db1 = weight_decay * &network.b1 + &grad.db1;
-            grad.db2 = weight_decay * &network.b2 + &grad.db2;
-            grad.dbatch_aff = weight_decay * &network.batch_aff + &grad.dbatch_aff;
-            sgd_w1.update(&mut network.w1, &grad.dw1);
-            sgd_b1.update(&mut network.b1, &grad.db1);
-            sgd_batch_aff.update(&mut network.batch_aff, &grad.dbatch_aff);
-            sgd_w2.update(&mut network.w2, &grad.dw2);
-            sgd_b2.update(&mut network.b2, &grad.db2);
-        }
-        // 検証データで評価
-        let val_loss = network.loss(&x_val, &t_val);
-        let val_acc = network.accuracy(&x_val, &t_val);
-        println!("val_loss: {:?}, val_acc: {:?}", val_loss, val_acc);
-        println!("{}", separator());
-        val_results.push((val_loss, learning_rate, weight_decay));
+    if let Err(e) = results.write_csv("hyperparameter_search.csv") {
+        eprintln!("failed to write search results: {}", e);
     }
-
-    // val_resultsを、第一要素降順でソート
-    val_results.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
-    println!("val_results: {:?}", val_results);
-    println!("choice: {:?}", val_results[0]);
-
-    // 一番良かった値を用いて本学習
-    let learning_rate = val_results[0].1;
-    let weight_decay = val_results[0].2;
-    let mut network = TwoLayerNet::new(
-        input_layer_size,
-        hidden_layer_size,
-        output_layer_size,
-        &Normal::new(0.0, 1.0 / (input_layer_size as f64)).unwrap(),
+    let best = results.best().unwrap();
+    println!(
+        "choice: {}, val_loss: {:?}, epochs: {:?}",
+        best.params, best.metric, best.budget
     );
-    let mut sgd_w1 = SGD::<Ix2>::new(learning_rate);
-    let mut sgd_b1 = SGD::<Ix1>::new(learning_rate);
-    let mut sgd_w2 = SGD::<Ix2>::new(learning_rate);
-    let mut sgd_b2 = SGD::<Ix1>::new(learning_rate);
-    let mut sgd_batch_aff = SGD::<Ix1>::new(learning_rate);
-
-    for _ in 0..iters_num {
-        let batch_mask = all_indexes
-            .iter()
-            .choose_multiple(&mut rng, batch_size as usize)
-            .iter()
-            .map(|&i| *i)
-            .collect::<Vec<usize>>();
-        let x_batch = x_train.select(Axis(0), &batch_mask);
-        let t_batch = t_train.select(Axis(0), &batch_mask);
 
-        let mut grad = network.gradient(&x_batch, &t_batch);
-        grad.dw1 = weight_decay * &network.w1 + &grad.dw1;
-        grad.dw2 = weight_decay * &network.w2 + &grad.dw2;
-        grad.db1 = weight_decay * &network.b1 + &grad.db1;
-        grad.db2 = weight_decay * &network.b2 + &grad.db2;
-        grad.dbatch_aff = weight_decay * &network.batch_aff + &grad.dbatch_aff;
-        sgd_w1.update(&mut network.w1, &grad.dw1);
-        sgd_b1.update(&mut network.b1, &grad.db1);
-        sgd_batch_aff.update(&mut network.batch_aff, &grad.dbatch_aff);
-        sgd_w2.update(&mut network.w2, &grad.dw2);
-        sgd_b2.update(&mut network.b2, &grad.db2);
+    // 一番良かった値を用いて本学習
+    let learning_rate = best.params.f64("learning_rate");
+    let weight_decay = best.params.f64("weight_decay");
+    // 検証データでの損失が3エポック改善しなければ打ち切り、最も良かったパラメータに戻す
+    let mut trainer = create_trainer(learning_rate, weight_decay, train_loader)
+        .with_validation(x_val, t_val, ValidationSchedule::EveryEpochs(1))
+        .with_early_stopping(EarlyStopping::new(Monitor::ValidationLoss, 3, 1e-4));
+    trainer.on_epoch_end(|e| {
+        if let Some(validation) = e.validation {
+            println!(
+                "epoch: {}, loss: {:?}, val_loss: {:?}, val_acc: {:?}",
+                e.epoch, e.loss, validation.loss, validation.accuracy
+            );
+        }
+    });
+    trainer.fit(epochs);
+    // 最後のエポックまで学習した場合も、最も良かったパラメータで評価する
+    trainer.restore_best_weights();
+    if trainer.stopped_early() {
+        println!("stopped early");
+    }
+    if let Some(best) = trainer.early_stopping().and_then(|e| e.best()) {
+        println!("restored epoch: {}", best.epoch);
+    }
+    // 学習曲線をimagesに書き出す
+    if let Err(e) = save_training_curves(trainer.history(), "images") {
+        eprintln!("failed to plot training curves: {}", e);
     }
     // テストデータで評価
-    let test_loss = network.loss(&x_test, &t_test);
-    let test_acc = network.accuracy(&x_test, &t_test);
+    let Evaluation {
+        loss: test_loss,
+        accuracy: test_acc,
+        ..
+    } = trainer.evaluate(&x_test, &t_test);
     println!("test_loss: {:?}, test_acc: {:?}", test_loss, test_acc);
+
+    // 学習したパラメータを保存し、後から評価や追加の学習に使えるようにする
+    if let Err(e) = Checkpoint::from_model(trainer.model_mut())
+        .with_optimizer(trainer.optimizer())
+        .save("two_layer_net.ckpt")
+    {
+        eprintln!("failed to save checkpoint: {}", e);
+    }
 }
@@ -1 +1,5 @@
+pub mod dataset_error;
+pub mod idx;
 pub mod load_mnist;
+pub mod mnist_paths;
+pub mod sampling;
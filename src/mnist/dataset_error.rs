@@ -0,0 +1,111 @@
+use std::{fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    MissingFile(PathBuf),
+    BadMagic {
+        path: PathBuf,
+        magic: u32,
+    },
+    /// u8(0x08)以外のデータ型
+    UnsupportedType {
+        path: PathBuf,
+        type_code: u8,
+    },
+    /// 次元数が、画像(3次元)やラベル(1次元)として期待するものと一致しない
+    UnexpectedDimensions {
+        path: PathBuf,
+        expected: usize,
+        found: usize,
+    },
+    /// ヘッダの次元から求めたデータのバイト数と、実際のバイト数が一致しない
+    SizeMismatch {
+        path: PathBuf,
+        expected: usize,
+        found: usize,
+    },
+    /// 画像の枚数とラベルの数が一致しない
+    LabelCountMismatch {
+        path: PathBuf,
+        images: usize,
+        labels: usize,
+    },
+    /// 要求された分割のサイズが、ファイルに含まれるデータの数を超えている
+    SplitTooLarge {
+        split: &'static str,
+        requested: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(e) => write!(f, "I/O error: {}", e),
+            DatasetError::MissingFile(path) => write!(f, "{} does not exist", path.display()),
+            DatasetError::BadMagic { path, magic } => write!(
+                f,
+                "{} is not an IDX file (magic number {:#010x})",
+                path.display(),
+                magic
+            ),
+            DatasetError::UnsupportedType { path, type_code } => write!(
+                f,
+                "{} has unsupported data type {:#04x}, expected unsigned byte (0x08)",
+                path.display(),
+                type_code
+            ),
+            DatasetError::UnexpectedDimensions {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has {} dimensions, expected {}",
+                path.display(),
+                found,
+                expected
+            ),
+            DatasetError::SizeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} should contain {} bytes of data, found {}",
+                path.display(),
+                expected,
+                found
+            ),
+            DatasetError::LabelCountMismatch {
+                path,
+                images,
+                labels,
+            } => write!(
+                f,
+                "{} has {} labels, but there are {} images",
+                path.display(),
+                labels,
+                images
+            ),
+            DatasetError::SplitTooLarge {
+                split,
+                requested,
+                available,
+            } => write!(
+                f,
+                "requested {} {} samples, but only {} are available",
+                requested, split, available
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+impl From<io::Error> for DatasetError {
+    fn from(e: io::Error) -> Self {
+        DatasetError::Io(e)
+    }
+}
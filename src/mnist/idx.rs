@@ -0,0 +1,218 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use flate2::read::GzDecoder;
+use ndarray::prelude::*;
+
+use super::dataset_error::DatasetError;
+
+/// IDX形式のファイルを読み込む
+///
+/// gzipで圧縮されたファイル(.gz)は、先頭のバイト列から判別して展開する。
+/// データ型はMNIST系のデータセットで使われるu8のみ対応している。
+pub fn read_idx(path: impl AsRef<Path>) -> Result<ArrayD<u8>, DatasetError> {
+    let path = path.as_ref();
+    let mut bytes = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut bytes)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(DatasetError::MissingFile(path.to_path_buf()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+        bytes = decoded;
+    }
+
+    // マジックナンバーは0x00, 0x00, データ型, 次元数の4バイト
+    let magic = bytes
+        .get(..4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+        .ok_or(DatasetError::SizeMismatch {
+            path: path.to_path_buf(),
+            expected: 4,
+            found: bytes.len(),
+        })?;
+    if magic >> 16 != 0 || magic & 0xff == 0 {
+        return Err(DatasetError::BadMagic {
+            path: path.to_path_buf(),
+            magic,
+        });
+    }
+    let type_code = (magic >> 8) as u8;
+    if type_code != 0x08 {
+        return Err(DatasetError::UnsupportedType {
+            path: path.to_path_buf(),
+            type_code,
+        });
+    }
+    let ndim = (magic & 0xff) as usize;
+    let data_offset = 4 + 4 * ndim;
+    let shape = bytes
+        .get(4..data_offset)
+        .ok_or(DatasetError::SizeMismatch {
+            path: path.to_path_buf(),
+            expected: data_offset,
+            found: bytes.len(),
+        })?
+        .chunks_exact(4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+        .collect::<Vec<_>>();
+    let data = bytes.split_off(data_offset);
+    let expected = shape.iter().try_fold(1usize, |len, &n| len.checked_mul(n));
+    if expected != Some(data.len()) {
+        return Err(DatasetError::SizeMismatch {
+            path: path.to_path_buf(),
+            expected: expected.unwrap_or(usize::MAX),
+            found: data.len(),
+        });
+    }
+    Ok(ArrayD::from_shape_vec(shape, data).unwrap())
+}
+
+/// IDX形式の画像ファイルを読み込み、各画像を1行に並べて[0,256)から[0.0,1.0)に正規化する
+///
+/// 戻り値の形状は(画像の枚数, 高さ*幅)。
+pub fn read_idx_images(path: impl AsRef<Path>) -> Result<Array2<f64>, DatasetError> {
+    let images = read_idx(&path)?;
+    if images.ndim() != 3 {
+        return Err(DatasetError::UnexpectedDimensions {
+            path: path.as_ref().to_path_buf(),
+            expected: 3,
+            found: images.ndim(),
+        });
+    }
+    let (n, h, w) = (images.shape()[0], images.shape()[1], images.shape()[2]);
+    Ok(images
+        .into_shape((n, h * w))
+        .unwrap()
+        .mapv(|x| x as f64 / 256.0))
+}
+
+/// IDX形式のラベルファイルを読み込む
+pub fn read_idx_labels(path: impl AsRef<Path>) -> Result<Array1<u8>, DatasetError> {
+    let labels = read_idx(&path)?;
+    let ndim = labels.ndim();
+    labels
+        .into_dimensionality::<Ix1>()
+        .map_err(|_| DatasetError::UnexpectedDimensions {
+            path: path.as_ref().to_path_buf(),
+            expected: 1,
+            found: ndim,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    /// u8の配列のIDXファイルのバイト列
+    fn idx_bytes(shape: &[u32], data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0x08, shape.len() as u8];
+        for &n in shape {
+            bytes.extend_from_slice(&n.to_be_bytes());
+        }
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// bytesを一時ファイルに書き出してreadで読み込む
+    fn read_from<T>(
+        name: &str,
+        bytes: &[u8],
+        read: impl FnOnce(&Path) -> Result<T, DatasetError>,
+    ) -> Result<T, DatasetError> {
+        let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        let result = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn reads_plain_and_gzipped_files() {
+        let images = idx_bytes(&[2, 2, 3], &[0, 64, 128, 255, 1, 2, 3, 4, 5, 6, 7, 8]);
+        let labels = idx_bytes(&[3], &[7, 0, 9]);
+        for (suffix, images, labels) in [
+            ("idx", images.clone(), labels.clone()),
+            ("gz", gzip(&images), gzip(&labels)),
+        ] {
+            let x = read_from(&format!("images.{}", suffix), &images, |p| {
+                read_idx_images(p)
+            })
+            .unwrap();
+            assert_eq!(x.dim(), (2, 6));
+            assert_eq!(
+                x.row(0).to_vec(),
+                vec![0.0, 0.25, 0.5, 255.0 / 256.0, 1.0 / 256.0, 2.0 / 256.0]
+            );
+            let t = read_from(&format!("labels.{}", suffix), &labels, |p| {
+                read_idx_labels(p)
+            })
+            .unwrap();
+            assert_eq!(t, arr1(&[7, 0, 9]));
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let read = |name: &str, bytes: &[u8]| read_from(name, bytes, |p| read_idx(p));
+        assert!(matches!(
+            read("truncated.idx", &idx_bytes(&[2, 3], &[0; 5])),
+            Err(DatasetError::SizeMismatch {
+                expected: 6,
+                found: 5,
+                ..
+            })
+        ));
+        assert!(matches!(
+            read(
+                "overflow.idx",
+                &idx_bytes(&[u32::MAX, u32::MAX, u32::MAX], &[])
+            ),
+            Err(DatasetError::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            read("short_header.idx", &idx_bytes(&[2, 3], &[])[..8]),
+            Err(DatasetError::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            read("magic.idx", &[1, 0, 8, 1, 0, 0, 0, 0]),
+            Err(DatasetError::BadMagic { .. })
+        ));
+        assert!(matches!(
+            read("float.idx", &[0, 0, 0x0d, 1, 0, 0, 0, 0]),
+            Err(DatasetError::UnsupportedType {
+                type_code: 0x0d,
+                ..
+            })
+        ));
+        assert!(matches!(
+            read_from("labels_2d.idx", &idx_bytes(&[1, 1], &[0]), |p| {
+                read_idx_labels(p)
+            }),
+            Err(DatasetError::UnexpectedDimensions {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            read_idx(std::env::temp_dir().join("no_such_file.idx")),
+            Err(DatasetError::MissingFile(_))
+        ));
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+/// MNIST形式のデータセット(MNIST、Fashion-MNIST、KMNISTなど)の各ファイルのパス
+#[derive(Clone, Debug)]
+pub struct MnistPaths {
+    pub train_images: PathBuf,
+    pub train_labels: PathBuf,
+    pub test_images: PathBuf,
+    pub test_labels: PathBuf,
+}
+
+impl MnistPaths {
+    /// ディレクトリ内の標準のファイル名(train-images-idx3-ubyteなど)のパスを返す
+    ///
+    /// 展開済みのファイルがなく.gzのファイルがある場合は、そちらを使う。
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        let find = |name: &str| {
+            let path = dir.join(name);
+            let gz = dir.join(format!("{}.gz", name));
+            if !path.exists() && gz.exists() {
+                gz
+            } else {
+                path
+            }
+        };
+        MnistPaths {
+            train_images: find("train-images-idx3-ubyte"),
+            train_labels: find("train-labels-idx1-ubyte"),
+            test_images: find("t10k-images-idx3-ubyte"),
+            test_labels: find("t10k-labels-idx1-ubyte"),
+        }
+    }
+}
+
+impl Default for MnistPaths {
+    fn default() -> Self {
+        MnistPaths::new("data")
+    }
+}
@@ -0,0 +1,161 @@
+use ndarray::prelude::*;
+use ndarray_rand::rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// 各分割に含めるデータの選び方
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampling {
+    /// ファイルの先頭から順に取り出す
+    #[default]
+    First,
+    /// シャッフルしてから取り出す
+    Random { seed: u64 },
+    /// ラベルごとの割合がファイル全体と同じになるように、ランダムに取り出す
+    Stratified { seed: u64 },
+}
+
+impl Sampling {
+    /// 1つのファイルから、sizesのそれぞれの大きさの互いに重ならないインデックスの組を選ぶ
+    ///
+    /// sizesの合計はlabelsの長さ以下でなければならない。
+    pub fn split_indexes(&self, labels: &Array1<u8>, sizes: &[usize]) -> Vec<Vec<usize>> {
+        match *self {
+            Sampling::First => {
+                let mut start = 0;
+                sizes
+                    .iter()
+                    .map(|&size| {
+                        start += size;
+                        (start - size..start).collect()
+                    })
+                    .collect()
+            }
+            Sampling::Random { seed } => {
+                let mut indexes = (0..labels.len()).collect::<Vec<_>>();
+                indexes.shuffle(&mut StdRng::seed_from_u64(seed));
+                let mut start = 0;
+                sizes
+                    .iter()
+                    .map(|&size| {
+                        start += size;
+                        indexes[start - size..start].to_vec()
+                    })
+                    .collect()
+            }
+            Sampling::Stratified { seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let classes = labels.iter().map(|&l| l as usize + 1).max().unwrap_or(0);
+                let mut pools = vec![Vec::new(); classes];
+                for (i, &label) in labels.iter().enumerate() {
+                    pools[label as usize].push(i);
+                }
+                for pool in pools.iter_mut() {
+                    pool.shuffle(&mut rng);
+                }
+                sizes
+                    .iter()
+                    .map(|&size| {
+                        let mut indexes = Vec::with_capacity(size);
+                        let counts = allocate(&pools, size);
+                        for (pool, count) in pools.iter_mut().zip(counts) {
+                            indexes.extend(pool.drain(..count));
+                        }
+                        indexes.shuffle(&mut rng);
+                        indexes
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// 残っているデータの数に比例するように、sizeをラベルごとに割り振る(最大剰余方式)
+fn allocate(pools: &[Vec<usize>], size: usize) -> Vec<usize> {
+    let remaining = pools.iter().map(Vec::len).sum::<usize>();
+    if remaining == 0 {
+        return vec![0; pools.len()];
+    }
+    let mut counts = pools
+        .iter()
+        .map(|pool| size * pool.len() / remaining)
+        .collect::<Vec<_>>();
+    let mut by_remainder = (0..pools.len()).collect::<Vec<_>>();
+    by_remainder.sort_by_key(|&c| std::cmp::Reverse(size * pools[c].len() % remaining));
+    let shortage = size - counts.iter().sum::<usize>();
+    for &c in by_remainder.iter().take(shortage) {
+        counts[c] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ラベル0が60個、1が30個、2が10個
+    fn labels() -> Array1<u8> {
+        (0..100)
+            .map(|i| match i % 10 {
+                0..=5 => 0,
+                6..=8 => 1,
+                _ => 2,
+            })
+            .collect()
+    }
+
+    fn count(labels: &Array1<u8>, indexes: &[usize], label: u8) -> usize {
+        indexes.iter().filter(|&&i| labels[i] == label).count()
+    }
+
+    #[test]
+    fn splits_are_disjoint_and_sized() {
+        let labels = labels();
+        for sampling in [
+            Sampling::First,
+            Sampling::Random { seed: 1 },
+            Sampling::Stratified { seed: 1 },
+        ] {
+            let splits = sampling.split_indexes(&labels, &[50, 30, 20]);
+            assert_eq!(
+                splits.iter().map(Vec::len).collect::<Vec<_>>(),
+                vec![50, 30, 20]
+            );
+            let mut all = splits.concat();
+            all.sort();
+            assert_eq!(all, (0..100).collect::<Vec<_>>(), "{:?}", sampling);
+        }
+        assert_eq!(
+            Sampling::First.split_indexes(&labels, &[2, 3]),
+            vec![vec![0, 1], vec![2, 3, 4]]
+        );
+    }
+
+    #[test]
+    fn stratified_keeps_label_proportions() {
+        let labels = labels();
+        let splits = Sampling::Stratified { seed: 3 }.split_indexes(&labels, &[50, 20, 10]);
+        for (split, expected) in splits.iter().zip([[30, 15, 5], [12, 6, 2], [6, 3, 1]]) {
+            for (label, expected) in expected.into_iter().enumerate() {
+                assert_eq!(count(&labels, split, label as u8), expected);
+            }
+        }
+        // 割り切れない場合は、余りの大きいラベルに1つずつ割り振る
+        let split = &Sampling::Stratified { seed: 3 }.split_indexes(&labels, &[7])[0];
+        assert_eq!(
+            (0..3).map(|l| count(&labels, split, l)).collect::<Vec<_>>(),
+            vec![4, 2, 1]
+        );
+    }
+
+    #[test]
+    fn seeded_sampling_is_reproducible() {
+        let labels = labels();
+        for sampling in [
+            |seed| Sampling::Random { seed },
+            |seed| Sampling::Stratified { seed },
+        ] {
+            let a = sampling(5).split_indexes(&labels, &[30, 10]);
+            assert_eq!(a, sampling(5).split_indexes(&labels, &[30, 10]));
+            assert_ne!(a, sampling(6).split_indexes(&labels, &[30, 10]));
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use ndarray::{Array2, ArrayViewMutD};
+
+use crate::layer::layer::Layer;
+
+/// 名前のついた学習可能なパラメータと、その勾配
+pub struct Parameter<'a> {
+    pub name: String,
+    pub value: ArrayViewMutD<'a, f64>,
+    pub grad: ArrayViewMutD<'a, f64>,
+}
+
+impl<'a> Parameter<'a> {
+    pub fn new(
+        name: impl Into<String>,
+        value: ArrayViewMutD<'a, f64>,
+        grad: ArrayViewMutD<'a, f64>,
+    ) -> Self {
+        Parameter {
+            name: name.into(),
+            value,
+            grad,
+        }
+    }
+}
+
+/// 学習可能なパラメータを持つモデル
+pub trait Model {
+    /// 全てのパラメータを、直前に求めた勾配と共に返す。名前はモデル内で一意である
+    fn parameters(&mut self) -> Vec<Parameter<'_>>;
+    /// バッチ正規化の移動平均など、学習で更新されるが勾配を持たない状態を返す
+    fn buffers(&mut self) -> Vec<(String, ArrayViewMutD<'_, f64>)> {
+        Vec::new()
+    }
+}
+
+/// 分類の学習と評価ができるネットワーク
+pub trait Network: Model {
+    /// softmaxを適用する前の出力を求める
+    fn predict(&mut self, x: &Array2<f64>, train_flg: bool) -> Array2<f64>;
+    /// 損失関数の層lossを用いて誤差逆伝播法で勾配を求め、(正則化項を含む損失, 学習時の出力)を返す
+    fn gradient_with_loss(
+        &mut self,
+        x: &Array2<f64>,
+        loss: &mut dyn Layer<Array2<f64>, f64>,
+    ) -> (f64, Array2<f64>);
+    /// 正則化項の値。正則化しない場合は0
+    fn penalty(&mut self) -> f64;
+}
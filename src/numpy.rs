@@ -0,0 +1,3 @@
+pub mod npy;
+pub mod npy_error;
+pub mod npz;
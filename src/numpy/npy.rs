@@ -0,0 +1,289 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use ndarray::{Array, ArrayD, ArrayView, Dimension, IxDyn};
+
+use super::npy_error::NpyError;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// .npyファイルを読み込む
+///
+/// データ型はf8(f64)とf4(f32)に対応し、f32はf64に変換する。fortran_orderの配列にも対応する。
+///
+/// # Examples
+/// ```no_run
+/// # use ndarray::Array2;
+/// # use zero_deeplearning::numpy::{self, npy_error::NpyError};
+/// # fn main() -> Result<(), NpyError> {
+///     let w1: Array2<f64> = numpy::npy::read_npy("W1.npy")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_npy<D: Dimension>(path: impl AsRef<Path>) -> Result<Array<f64, D>, NpyError> {
+    let name = path.as_ref().display().to_string();
+    let array = read_npy_from(&mut BufReader::new(File::open(path)?))?;
+    into_dimensionality(array, &name)
+}
+
+/// 配列を.npyファイル(バージョン1.0、リトルエンディアンのf8、C order)として書き出す
+pub fn write_npy<D: Dimension>(
+    path: impl AsRef<Path>,
+    array: ArrayView<f64, D>,
+) -> Result<(), NpyError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_npy_to(&mut writer, array)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_npy_from(reader: &mut impl Read) -> Result<ArrayD<f64>, NpyError> {
+    let mut magic = [0; 6];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(NpyError::BadMagic);
+    }
+    let mut version = [0; 2];
+    reader.read_exact(&mut version)?;
+    let header_len = match version {
+        [1, 0] => {
+            let mut buf = [0; 2];
+            reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf) as usize
+        }
+        [2, 0] | [3, 0] => {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf) as usize
+        }
+        [major, minor] => return Err(NpyError::UnsupportedVersion(major, minor)),
+    };
+    let header = read_bytes(reader, header_len)?;
+    let header = String::from_utf8_lossy(&header).to_string();
+    let (descr, fortran_order, shape) = parse_header(&header)?;
+
+    let too_large = || NpyError::InvalidHeader(format!("array of shape {:?} is too large", shape));
+    let len = shape
+        .iter()
+        .try_fold(1usize, |len, &n| len.checked_mul(n))
+        .ok_or_else(too_large)?;
+    let data = match descr.as_str() {
+        "<f8" | "|f8" | ">f8" => {
+            let buf = read_bytes(reader, len.checked_mul(8).ok_or_else(too_large)?)?;
+            buf.chunks_exact(8)
+                .map(|b| {
+                    let b = b.try_into().unwrap();
+                    if descr.starts_with('>') {
+                        f64::from_be_bytes(b)
+                    } else {
+                        f64::from_le_bytes(b)
+                    }
+                })
+                .collect::<Vec<f64>>()
+        }
+        "<f4" | "|f4" | ">f4" => {
+            let buf = read_bytes(reader, len.checked_mul(4).ok_or_else(too_large)?)?;
+            buf.chunks_exact(4)
+                .map(|b| {
+                    let b = b.try_into().unwrap();
+                    if descr.starts_with('>') {
+                        f32::from_be_bytes(b) as f64
+                    } else {
+                        f32::from_le_bytes(b) as f64
+                    }
+                })
+                .collect::<Vec<f64>>()
+        }
+        _ => return Err(NpyError::UnsupportedDtype(descr)),
+    };
+    if fortran_order {
+        // Fortran orderの配列は、形状を逆にしたC orderの配列の転置になっている
+        let reversed = shape.iter().rev().cloned().collect::<Vec<usize>>();
+        let array = ArrayD::from_shape_vec(IxDyn(&reversed), data)
+            .map_err(|e| NpyError::InvalidHeader(e.to_string()))?;
+        Ok(array.reversed_axes().as_standard_layout().into_owned())
+    } else {
+        ArrayD::from_shape_vec(IxDyn(&shape), data)
+            .map_err(|e| NpyError::InvalidHeader(e.to_string()))
+    }
+}
+
+/// readerからlenバイトを読む
+///
+/// ヘッダに書かれた長さを信用して先に確保することはせず、実際に読めた分だけ確保する。
+fn read_bytes(reader: &mut impl Read, len: usize) -> Result<Vec<u8>, NpyError> {
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(buf)
+}
+
+pub fn write_npy_to<D: Dimension>(
+    writer: &mut impl Write,
+    array: ArrayView<f64, D>,
+) -> Result<(), NpyError> {
+    let shape = match array.shape() {
+        [len] => format!("({},)", len),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|len| len.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+    // マジックナンバーからヘッダの終わりの改行までが64バイトの倍数になるよう空白で埋める
+    let unpadded_len = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded_len % 64) % 64));
+    header.push('\n');
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    // iterは論理的な順序(C order)で要素を返す
+    for x in array.iter() {
+        writer.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub(super) fn into_dimensionality<D: Dimension>(
+    array: ArrayD<f64>,
+    name: &str,
+) -> Result<Array<f64, D>, NpyError> {
+    let ndim = array.ndim();
+    array
+        .into_dimensionality::<D>()
+        .map_err(|_| NpyError::DimensionMismatch {
+            name: name.to_string(),
+            expected: D::NDIM.unwrap_or(ndim),
+            found: ndim,
+        })
+}
+
+/// "{'descr': '<f8', 'fortran_order': False, 'shape': (3, 4), }"の形のヘッダを読む
+fn parse_header(header: &str) -> Result<(String, bool, Vec<usize>), NpyError> {
+    let invalid = || NpyError::InvalidHeader(header.trim().to_string());
+    let value_of = |key: &str| -> Result<&str, NpyError> {
+        let start = header.find(&format!("'{}'", key)).ok_or_else(invalid)? + key.len() + 2;
+        let rest = header[start..]
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(invalid)?;
+        Ok(rest.trim_start())
+    };
+
+    let descr = value_of("descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|descr| descr.split('\'').next())
+        .ok_or_else(invalid)?
+        .to_string();
+
+    let fortran_order = value_of("fortran_order")?.starts_with("True");
+
+    let shape = value_of("shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|shape| shape.split(')').next())
+        .ok_or_else(invalid)?;
+    let shape = shape
+        .split(',')
+        .map(|len| len.trim())
+        .filter(|len| !len.is_empty())
+        .map(|len| len.parse::<usize>().map_err(|_| invalid()))
+        .collect::<Result<Vec<usize>, NpyError>>()?;
+    Ok((descr, fortran_order, shape))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr2, Array3};
+
+    /// ヘッダとデータを並べた.npy(バージョン1.0)のバイト列を作る
+    fn npy_bytes(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let array = Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 12 + j * 4 + k) as f64 - 0.5);
+        let mut bytes = Vec::new();
+        // 転置したビューもC orderで書き出される
+        write_npy_to(&mut bytes, array.view().reversed_axes()).unwrap();
+        assert_eq!((MAGIC.len() + 4 + bytes[8] as usize) % 64, 0);
+        let read = read_npy_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, array.reversed_axes().into_dyn());
+
+        let mut bytes = Vec::new();
+        write_npy_to(&mut bytes, arr1(&[1.0, 2.0]).view()).unwrap();
+        assert_eq!(
+            read_npy_from(&mut bytes.as_slice()).unwrap(),
+            arr1(&[1.0, 2.0]).into_dyn()
+        );
+    }
+
+    #[test]
+    fn reads_numpy_format_fixture() {
+        // NumPyを使わずにtestdata/generate.pyで作った、np.save("save.npy", np.array([[0.5, -1.0, 2.0], [3.25, 0.0, -0.125]]))と
+        // 同じバイト列を再現したもの(配列を拡張できるようにヘッダを余分に空白で埋めている)
+        let bytes = include_bytes!("testdata/save.npy");
+        let array = read_npy_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            array,
+            arr2(&[[0.5, -1.0, 2.0], [3.25, 0.0, -0.125]]).into_dyn()
+        );
+    }
+
+    #[test]
+    fn reads_f32_and_fortran_order() {
+        let data = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .flat_map(|x| x.to_be_bytes())
+            .collect::<Vec<u8>>();
+        let bytes = npy_bytes(
+            "{'descr': '>f4', 'fortran_order': True, 'shape': (2, 3), }\n",
+            &data,
+        );
+        let array = read_npy_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(array, arr2(&[[1.0, 3.0, 5.0], [2.0, 4.0, 6.0]]).into_dyn());
+    }
+
+    #[test]
+    fn huge_shapes_are_rejected_without_allocating() {
+        let overflow = npy_bytes(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (4294967296, 4294967296), }\n",
+            &[],
+        );
+        assert!(matches!(
+            read_npy_from(&mut overflow.as_slice()),
+            Err(NpyError::InvalidHeader(_))
+        ));
+        // 1TB分のデータがあるとするヘッダでも、実際のデータの分しか読まない
+        let truncated = npy_bytes(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (137438953472,), }\n",
+            &[0; 16],
+        );
+        match read_npy_from(&mut truncated.as_slice()) {
+            Err(NpyError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
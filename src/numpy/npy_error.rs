@@ -0,0 +1,68 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum NpyError {
+    Io(io::Error),
+    /// .npyのファイルではない
+    BadMagic,
+    UnsupportedVersion(u8, u8),
+    /// f64に変換できないデータ型
+    UnsupportedDtype(String),
+    InvalidHeader(String),
+    /// 配列の次元数が、読み込み先の次元数と一致しない
+    DimensionMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    ShapeMismatch {
+        name: String,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// .npzに必要な配列がない
+    MissingArray(String),
+    InvalidZip(String),
+}
+
+impl fmt::Display for NpyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpyError::Io(e) => write!(f, "I/O error: {}", e),
+            NpyError::BadMagic => write!(f, "not a .npy file"),
+            NpyError::UnsupportedVersion(major, minor) => {
+                write!(f, "unsupported .npy version {}.{}", major, minor)
+            }
+            NpyError::UnsupportedDtype(descr) => write!(f, "unsupported dtype `{}`", descr),
+            NpyError::InvalidHeader(header) => write!(f, "invalid .npy header: {}", header),
+            NpyError::DimensionMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` has {} dimensions, expected {}",
+                name, found, expected
+            ),
+            NpyError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "shape mismatch for `{}`: expected {:?}, found {:?}",
+                name, expected, found
+            ),
+            NpyError::MissingArray(name) => write!(f, "missing array `{}`", name),
+            NpyError::InvalidZip(message) => write!(f, "invalid .npz archive: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for NpyError {}
+
+impl From<io::Error> for NpyError {
+    fn from(e: io::Error) -> Self {
+        NpyError::Io(e)
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use ndarray::{Array, ArrayD, ArrayViewD, Dimension, IxDyn};
+
+use super::{
+    npy::{into_dimensionality, read_npy_from, write_npy_to},
+    npy_error::NpyError,
+};
+use crate::model::Model;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+/// .npzファイル(np.savez、np.savez_compressedで作られるzip)に含まれる全ての配列を読み込む
+///
+/// 配列の名前は、zip内のファイル名から".npy"を除いたものになる。
+pub fn read_npz(path: impl AsRef<Path>) -> Result<Vec<(String, ArrayD<f64>)>, NpyError> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    read_npz_bytes(&bytes)
+}
+
+fn read_npz_bytes(bytes: &[u8]) -> Result<Vec<(String, ArrayD<f64>)>, NpyError> {
+    read_zip_entries(bytes)?
+        .into_iter()
+        .map(|(name, data)| {
+            let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+            Ok((name, read_npy_from(&mut data.as_slice())?))
+        })
+        .collect()
+}
+
+/// 配列を、np.loadで読み込める非圧縮の.npzファイルとして書き出す
+pub fn write_npz(
+    path: impl AsRef<Path>,
+    arrays: &[(String, ArrayViewD<f64>)],
+) -> Result<(), NpyError> {
+    let entries = arrays
+        .iter()
+        .map(|(name, array)| {
+            let mut data = Vec::new();
+            write_npy_to(&mut data, array.view())?;
+            Ok((format!("{}.npy", name), data))
+        })
+        .collect::<Result<Vec<(String, Vec<u8>)>, NpyError>>()?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_zip_entries(&mut writer, &entries, false)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// read_npzで読み込んだ配列から、名前が`name`のものを取り出す
+pub fn npz_entry<D: Dimension>(
+    arrays: &[(String, ArrayD<f64>)],
+    name: &str,
+) -> Result<Array<f64, D>, NpyError> {
+    let (_, array) = arrays
+        .iter()
+        .find(|(entry_name, _)| entry_name == name)
+        .ok_or_else(|| NpyError::MissingArray(name.to_string()))?;
+    into_dimensionality(array.clone(), name)
+}
+
+/// モデルの全てのパラメータとバッファを、モデル内での名前で.npzファイルに書き出す
+pub fn save_model_npz(model: &mut impl Model, path: impl AsRef<Path>) -> Result<(), NpyError> {
+    let mut arrays = model
+        .parameters()
+        .into_iter()
+        .map(|param| (param.name, param.value.to_owned()))
+        .collect::<Vec<(String, ArrayD<f64>)>>();
+    arrays.extend(
+        model
+            .buffers()
+            .into_iter()
+            .map(|(name, buffer)| (name, buffer.to_owned())),
+    );
+    let views = arrays
+        .iter()
+        .map(|(name, array)| (name.clone(), array.view()))
+        .collect::<Vec<_>>();
+    write_npz(path, &views)
+}
+
+/// save_model_npzで書き出した.npzファイルから、モデルのパラメータとバッファを読み込む
+///
+/// .npzにない名前や形状の異なる配列があった場合は、モデルを変更せずにエラーを返す。
+pub fn load_model_npz(model: &mut impl Model, path: impl AsRef<Path>) -> Result<(), NpyError> {
+    let arrays = read_npz(path)?;
+    // 全ての配列を確認してから書き込み、途中で失敗してもモデルが変更されないようにする
+    let mut shapes = model
+        .parameters()
+        .into_iter()
+        .map(|param| (param.name, param.value.shape().to_vec()))
+        .collect::<Vec<_>>();
+    shapes.extend(
+        model
+            .buffers()
+            .into_iter()
+            .map(|(name, buffer)| (name, buffer.shape().to_vec())),
+    );
+    for (name, shape) in shapes.iter() {
+        let source = npz_entry::<IxDyn>(&arrays, name)?;
+        if source.shape() != shape.as_slice() {
+            return Err(NpyError::ShapeMismatch {
+                name: name.clone(),
+                expected: shape.clone(),
+                found: source.shape().to_vec(),
+            });
+        }
+    }
+
+    for mut param in model.parameters() {
+        param
+            .value
+            .assign(&npz_entry::<IxDyn>(&arrays, &param.name)?);
+    }
+    for (name, mut buffer) in model.buffers() {
+        buffer.assign(&npz_entry::<IxDyn>(&arrays, &name)?);
+    }
+    Ok(())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, NpyError> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| NpyError::InvalidZip("unexpected end of archive".to_string()))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, NpyError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| NpyError::InvalidZip("unexpected end of archive".to_string()))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64, NpyError> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| NpyError::InvalidZip("unexpected end of archive".to_string()))
+}
+
+/// zipのセントラルディレクトリを読み、(ファイル名, 展開したデータ)の列を返す
+///
+/// np.savezの非圧縮(stored)とnp.savez_compressedのdeflateに対応する。
+fn read_zip_entries(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, NpyError> {
+    // End of central directoryは末尾のコメント(最大65535バイト)の直前にある
+    let eocd = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .take(65_535 + 22)
+        .find(|&offset| u32_at(bytes, offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| NpyError::InvalidZip("end of central directory not found".to_string()))?;
+    let num_entries = u16_at(bytes, eocd + 10)? as usize;
+    let mut offset = u32_at(bytes, eocd + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(num_entries);
+    for _ in 0..num_entries {
+        if u32_at(bytes, offset)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(NpyError::InvalidZip(
+                "bad central directory header".to_string(),
+            ));
+        }
+        let method = u16_at(bytes, offset + 10)?;
+        let crc = u32_at(bytes, offset + 16)?;
+        let mut compressed_size = u32_at(bytes, offset + 20)? as u64;
+        let mut uncompressed_size = u32_at(bytes, offset + 24)? as u64;
+        let name_len = u16_at(bytes, offset + 28)? as usize;
+        let extra_len = u16_at(bytes, offset + 30)? as usize;
+        let comment_len = u16_at(bytes, offset + 32)? as usize;
+        let mut local_offset = u32_at(bytes, offset + 42)? as u64;
+        let name = bytes
+            .get(offset + 46..offset + 46 + name_len)
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .ok_or_else(|| NpyError::InvalidZip("unexpected end of archive".to_string()))?;
+
+        // 4GBを超える値はZIP64の拡張フィールドに、0xFFFFFFFFとなった項目の順で格納される
+        let mut extra = offset + 46 + name_len;
+        let extra_end = extra + extra_len;
+        while extra + 4 <= extra_end {
+            let id = u16_at(bytes, extra)?;
+            let size = u16_at(bytes, extra + 2)? as usize;
+            if id == 0x0001 {
+                let mut field = extra + 4;
+                for value in [
+                    &mut uncompressed_size,
+                    &mut compressed_size,
+                    &mut local_offset,
+                ] {
+                    if *value == 0xFFFF_FFFF {
+                        *value = u64_at(bytes, field)?;
+                        field += 8;
+                    }
+                }
+            }
+            extra += 4 + size;
+        }
+        offset = extra_end + comment_len;
+
+        let local_offset = local_offset as usize;
+        if u32_at(bytes, local_offset)? != LOCAL_HEADER_SIGNATURE {
+            return Err(NpyError::InvalidZip("bad local file header".to_string()));
+        }
+        let data_start = local_offset
+            + 30
+            + u16_at(bytes, local_offset + 26)? as usize
+            + u16_at(bytes, local_offset + 28)? as usize;
+        let data = usize::try_from(compressed_size)
+            .ok()
+            .and_then(|size| bytes.get(data_start..data_start.checked_add(size)?))
+            .ok_or_else(|| NpyError::InvalidZip("unexpected end of archive".to_string()))?;
+        let data = match method {
+            0 => data.to_vec(),
+            8 => {
+                // ヘッダの展開後のサイズでは確保せず、それを超えて展開もしない
+                let mut decoded = Vec::new();
+                DeflateDecoder::new(data)
+                    .take(uncompressed_size)
+                    .read_to_end(&mut decoded)?;
+                decoded
+            }
+            _ => {
+                return Err(NpyError::InvalidZip(format!(
+                    "unsupported compression method {}",
+                    method
+                )))
+            }
+        };
+        if data.len() as u64 != uncompressed_size {
+            return Err(NpyError::InvalidZip(format!(
+                "size of `{}` does not match the header",
+                name
+            )));
+        }
+        if crc32fast::hash(&data) != crc {
+            return Err(NpyError::InvalidZip(format!("CRC mismatch for `{}`", name)));
+        }
+        entries.push((name, data));
+    }
+    Ok(entries)
+}
+
+/// (ファイル名, データ)の列をzipとして書き出す
+///
+/// deflateがfalseの場合は非圧縮(stored)で格納する。
+fn write_zip_entries(
+    writer: &mut impl Write,
+    entries: &[(String, Vec<u8>)],
+    deflate: bool,
+) -> Result<(), NpyError> {
+    let mut central_directory = Vec::new();
+    let mut offset = 0u32;
+    for (name, data) in entries.iter() {
+        let crc = crc32fast::hash(data);
+        let size = data.len() as u32;
+        let (method, stored) = if deflate {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            (8u16, encoder.finish()?)
+        } else {
+            (0u16, data.clone())
+        };
+        let compressed_size = stored.len() as u32;
+        let name_len = name.len() as u16;
+
+        let mut local_header = Vec::new();
+        local_header.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        local_header.extend_from_slice(&20u16.to_le_bytes()); // 展開に必要なバージョン
+        local_header.extend_from_slice(&0u16.to_le_bytes()); // フラグ
+        local_header.extend_from_slice(&method.to_le_bytes()); // 圧縮方法
+        local_header.extend_from_slice(&0u16.to_le_bytes()); // 更新時刻
+        local_header.extend_from_slice(&0x21u16.to_le_bytes()); // 更新日付(1980-01-01)
+        local_header.extend_from_slice(&crc.to_le_bytes());
+        local_header.extend_from_slice(&compressed_size.to_le_bytes());
+        local_header.extend_from_slice(&size.to_le_bytes());
+        local_header.extend_from_slice(&name_len.to_le_bytes());
+        local_header.extend_from_slice(&0u16.to_le_bytes()); // 拡張フィールドの長さ
+        local_header.extend_from_slice(name.as_bytes());
+        writer.write_all(&local_header)?;
+        writer.write_all(&stored)?;
+
+        central_directory.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes()); // 作成したバージョン
+        central_directory.extend_from_slice(&local_header[4..30]);
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // コメントの長さ
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // ディスク番号
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // 内部属性
+        central_directory.extend_from_slice(&0u32.to_le_bytes()); // 外部属性
+        central_directory.extend_from_slice(&offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+
+        offset += local_header.len() as u32 + compressed_size;
+    }
+    writer.write_all(&central_directory)?;
+
+    let num_entries = entries.len() as u16;
+    writer.write_all(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?; // ディスク番号
+    writer.write_all(&0u16.to_le_bytes())?; // セントラルディレクトリの開始ディスク
+    writer.write_all(&num_entries.to_le_bytes())?;
+    writer.write_all(&num_entries.to_le_bytes())?;
+    writer.write_all(&(central_directory.len() as u32).to_le_bytes())?;
+    writer.write_all(&offset.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?; // コメントの長さ
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layer::{affine_layer::AffineLayer, batch_normalization_layer::BatchNormalizationLayer},
+        sequential::Sequential,
+    };
+    use ndarray::{arr1, arr2, stack, Array1, Array2, Axis, Ix1, Ix2};
+
+    fn entries() -> Vec<(String, Vec<u8>)> {
+        [
+            ("w.npy", arr2(&[[1.0, -2.0], [0.5, 3.0]]).into_dyn()),
+            ("b.npy", Array1::linspace(0.0, 1.0, 100).into_dyn()),
+        ]
+        .into_iter()
+        .map(|(name, array)| {
+            let mut data = Vec::new();
+            write_npy_to(&mut data, array.view()).unwrap();
+            (name.to_string(), data)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn stored_and_deflated_round_trip() {
+        for deflate in [false, true] {
+            let mut bytes = Vec::new();
+            write_zip_entries(&mut bytes, &entries(), deflate).unwrap();
+            assert_eq!(u16_at(&bytes, 8).unwrap(), if deflate { 8 } else { 0 });
+            assert_eq!(read_zip_entries(&bytes).unwrap(), entries());
+
+            let arrays = read_npz_bytes(&bytes).unwrap();
+            assert_eq!(
+                npz_entry::<Ix2>(&arrays, "w").unwrap(),
+                arr2(&[[1.0, -2.0], [0.5, 3.0]])
+            );
+            assert_eq!(
+                npz_entry::<Ix1>(&arrays, "b").unwrap(),
+                Array1::linspace(0.0, 1.0, 100)
+            );
+        }
+    }
+
+    #[test]
+    fn reads_numpy_format_fixtures() {
+        // NumPyを使わずにtestdata/generate.pyで、np.savez(w=..., b=...)とnp.savez_compressed(w=..., b=...)の
+        // バイト列の作り方を再現して作ったもの
+        // ローカルヘッダにZIP64の拡張フィールドがあり、bはf4で格納されている
+        for bytes in [
+            include_bytes!("testdata/savez.npz").as_slice(),
+            include_bytes!("testdata/savez_compressed.npz").as_slice(),
+        ] {
+            let arrays = read_npz_bytes(bytes).unwrap();
+            assert_eq!(
+                npz_entry::<Ix2>(&arrays, "w").unwrap(),
+                arr2(&[[0.5, -1.0, 2.0], [3.25, 0.0, -0.125]])
+            );
+            assert_eq!(
+                npz_entry::<Ix1>(&arrays, "b").unwrap(),
+                arr1(&[1.5, -2.0, 0.25])
+            );
+            assert!(matches!(
+                npz_entry::<Ix1>(&arrays, "w"),
+                Err(NpyError::DimensionMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn corrupted_archives_are_rejected() {
+        let mut bytes = Vec::new();
+        write_zip_entries(&mut bytes, &entries(), true).unwrap();
+        let central_directory = u32_at(&bytes, bytes.len() - 6).unwrap() as usize;
+
+        // 展開後のサイズを実際より大きく書き換えても、その分を確保せずにエラーにする
+        let mut oversized = bytes.clone();
+        oversized[central_directory + 24..central_directory + 28]
+            .copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            read_zip_entries(&oversized),
+            Err(NpyError::InvalidZip(_))
+        ));
+
+        let mut bad_crc = bytes.clone();
+        bad_crc[central_directory + 16] ^= 0xFF;
+        assert!(matches!(
+            read_zip_entries(&bad_crc),
+            Err(NpyError::InvalidZip(_))
+        ));
+
+        assert!(read_zip_entries(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn model_round_trip() {
+        let new_network = |w: Array2<f64>| {
+            let mut network = Sequential::new();
+            network
+                .add(AffineLayer::new_owned(w, arr1(&[0.1, 0.0])))
+                .add(BatchNormalizationLayer::new_owned(
+                    stack![Axis(0), Array1::ones(2), Array1::zeros(2)],
+                    arr1(&[0.5, -0.5]),
+                    Array1::ones(2),
+                    0.9,
+                ));
+            network
+        };
+        let path =
+            std::env::temp_dir().join(format!("model_round_trip_{}.npz", std::process::id()));
+        let mut saved = new_network(arr2(&[[0.2, -0.5], [0.4, 0.3]]));
+        save_model_npz(&mut saved, &path).unwrap();
+
+        let mut loaded = new_network(Array2::zeros((2, 2)));
+        load_model_npz(&mut loaded, &path).unwrap();
+        let mut other = Sequential::new();
+        other.add(AffineLayer::new_owned(
+            Array2::zeros((3, 2)),
+            Array1::zeros(2),
+        ));
+        let mismatch = load_model_npz(&mut other, &path);
+        std::fs::remove_file(&path).unwrap();
+
+        for (saved, loaded) in saved.parameters().iter().zip(loaded.parameters().iter()) {
+            assert_eq!(saved.name, loaded.name);
+            assert_eq!(saved.value, loaded.value);
+        }
+        assert_eq!(loaded.buffers()[0].1, arr1(&[0.5, -0.5]).into_dyn());
+        assert!(matches!(mismatch, Err(NpyError::ShapeMismatch { .. })));
+        assert_eq!(
+            other.parameters()[0].value,
+            Array2::<f64>::zeros((3, 2)).into_dyn()
+        );
+    }
+}
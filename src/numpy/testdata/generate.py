@@ -0,0 +1,36 @@
+# NumPy 1.26のnp.save、np.savez、np.savez_compressedと同じバイト列を、NumPyを使わずに書き出す。
+# numpy/lib/format.pyのヘッダの作り方と、npyio._savezのzipfileの使い方(force_zip64=True)を再現している。
+import io, os, struct, zipfile
+
+MAGIC = b'\x93NUMPY'
+ARRAY_ALIGN = 64
+GROWTH_AXIS_MAX_DIGITS = 21
+
+def header(descr, shape):
+    d = {'descr': descr, 'fortran_order': False, 'shape': shape}
+    h = ["{"]
+    for key, value in sorted(d.items()):
+        h.append("'%s': %s, " % (key, repr(value)))
+    h.append("}")
+    h = "".join(h)
+    h += " " * (GROWTH_AXIS_MAX_DIGITS - len(repr(shape[0] if shape else 0)))
+    h = h.encode('latin1')
+    hlen = len(h) + 1
+    padlen = ARRAY_ALIGN - ((len(MAGIC) + 2 + 2 + hlen) % ARRAY_ALIGN)
+    return MAGIC + b'\x01\x00' + struct.pack('<H', hlen + padlen) + h + b' ' * padlen + b'\n'
+
+def npy(descr, fmt, shape, values):
+    return header(descr, shape) + b''.join(struct.pack(fmt, v) for v in values)
+
+arrays = {
+    'w': npy('<f8', '<d', (2, 3), [0.5, -1.0, 2.0, 3.25, 0.0, -0.125]),
+    'b': npy('<f4', '<f', (3,), [1.5, -2.0, 0.25]),
+}
+for name, compression in [('savez.npz', zipfile.ZIP_STORED), ('savez_compressed.npz', zipfile.ZIP_DEFLATED)]:
+    buf = io.BytesIO()
+    with zipfile.ZipFile(buf, mode='w', compression=compression, allowZip64=True) as zipf:
+        for key, data in arrays.items():
+            with zipf.open(key + '.npy', 'w', force_zip64=True) as fid:
+                fid.write(data)
+    open(os.path.dirname(os.path.abspath(__file__)) + '/' + name, 'wb').write(buf.getvalue())
+open(os.path.dirname(os.path.abspath(__file__)) + '/save.npy', 'wb').write(arrays['w'])
@@ -1,4 +1,11 @@
 pub mod ada_grad;
+pub mod adam;
+pub mod adam_w;
+pub mod gradient_clipping;
 pub mod momentum;
+pub mod nesterov;
+#[allow(clippy::module_inception)]
 pub mod optimize;
+pub mod optimizer;
+pub mod rms_prop;
 pub mod sgd;
@@ -1,30 +1,48 @@
-use ndarray::{Array, Dimension};
+use ndarray::{Array, ArrayD, ArrayView, ArrayViewMut, Dimension};
 
-use super::optimize::Optimize;
+use super::optimize::{state_like, Optimize};
+use crate::checkpoint::{state_entry, CheckpointError};
 
 pub struct AdaGrad<D: Dimension> {
     learning_rate: f64,
-    h: Array<f64, D>,
+    h: Option<Array<f64, D>>,
 }
 
 impl<D: Dimension> AdaGrad<D> {
     pub fn new(learning_rate: f64) -> Self {
         Self {
             learning_rate,
-            h: Array::zeros(D::default()),
+            h: None,
         }
     }
 }
 
 impl<D: Dimension> Optimize<D> for AdaGrad<D> {
-    fn update(&mut self, w: &mut Array<f64, D>, grad: &Array<f64, D>) {
-        // 初めての呼び出し時はhのdimが(0,0,...)なので、wの形に揃える
-        if self.h.len() == 0 {
-            self.h = Array::zeros(w.raw_dim());
-        }
-        let h = &self.h + &(grad * grad);
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        let h = state_like(&mut self.h, &w);
+        *h += &(&grad * &grad);
         let h_sqrt = h.map(|x| x.sqrt() + 1e-7);
-        *w -= &(grad * self.learning_rate / h_sqrt);
-        self.h = h;
+        w -= &(&grad * self.learning_rate / h_sqrt);
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        self.h
+            .iter()
+            .map(|h| ("h".to_string(), h.clone().into_dyn()))
+            .collect()
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        // 一度も更新していない状態は空で保存される
+        self.h = if state.is_empty() {
+            None
+        } else {
+            Some(state_entry(state, "h")?)
+        };
+        Ok(())
     }
 }
@@ -0,0 +1,116 @@
+use ndarray::{arr0, Array, ArrayD, ArrayView, ArrayViewMut, Dimension, Ix0};
+
+use super::optimize::{state_like, Optimize};
+use crate::checkpoint::{state_entry, CheckpointError};
+
+pub struct Adam<D: Dimension> {
+    learning_rate: f64,
+    beta1: f64,
+    beta2: f64,
+    eps: f64,
+    iter: i32,
+    m: Option<Array<f64, D>>,
+    v: Option<Array<f64, D>>,
+}
+
+impl<D: Dimension> Adam<D> {
+    pub fn new(learning_rate: f64, beta1: f64, beta2: f64, eps: f64) -> Self {
+        Self {
+            learning_rate,
+            beta1,
+            beta2,
+            eps,
+            iter: 0,
+            m: None,
+            v: None,
+        }
+    }
+}
+
+impl<D: Dimension> Optimize<D> for Adam<D> {
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        let m = state_like(&mut self.m, &w);
+        *m = self.beta1 * &*m + (1.0 - self.beta1) * &grad;
+        let m_hat = &*m / (1.0 - self.beta1.powi(self.iter + 1));
+        let v = state_like(&mut self.v, &w);
+        *v = self.beta2 * &*v + (1.0 - self.beta2) * &(&grad * &grad);
+        // m,vは0で初期化しているため、学習の初期に0へ偏るのを補正する
+        let v_hat = &*v / (1.0 - self.beta2.powi(self.iter + 1));
+        self.iter += 1;
+        w -= &(self.learning_rate * m_hat / v_hat.mapv(|x| x.sqrt() + self.eps));
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        match (&self.m, &self.v) {
+            (Some(m), Some(v)) => vec![
+                ("m".to_string(), m.clone().into_dyn()),
+                ("v".to_string(), v.clone().into_dyn()),
+                ("iter".to_string(), arr0(self.iter as f64).into_dyn()),
+            ],
+            _ => Vec::new(),
+        }
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        // 一度も更新していない状態は空で保存される
+        if state.is_empty() {
+            (self.m, self.v, self.iter) = (None, None, 0);
+            return Ok(());
+        }
+        self.m = Some(state_entry(state, "m")?);
+        self.v = Some(state_entry(state, "v")?);
+        self.iter = state_entry::<Ix0>(state, "iter")?.into_scalar() as i32;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr2, IxDyn};
+
+    #[test]
+    fn first_step_moves_each_weight_by_learning_rate() {
+        // バイアス補正により、最初の更新量は勾配の大きさによらずほぼ学習率になる
+        let mut adam = Adam::new(0.01, 0.9, 0.999, 1e-8);
+        let mut w = arr1(&[1.0, 1.0, 1.0]);
+        adam.update(w.view_mut(), arr1(&[100.0, -0.01, 3.0]).view());
+        for (w, expected) in w.iter().zip([0.99, 1.01, 0.99]) {
+            assert!((w - expected).abs() < 1e-6, "{} != {}", w, expected);
+        }
+    }
+
+    #[test]
+    fn state_round_trip_continues_identically() {
+        let grads = [arr1(&[0.5, -1.0]), arr1(&[0.2, 0.3]), arr1(&[-0.7, 0.1])];
+        let mut adam = Adam::new(0.01, 0.9, 0.999, 1e-8);
+        let mut w = arr1(&[1.0, 2.0]);
+        adam.update(w.view_mut(), grads[0].view());
+        let mut resumed = Adam::new(0.01, 0.9, 0.999, 1e-8);
+        resumed.load_state(&adam.state()).unwrap();
+        let mut w_resumed = w.clone();
+        for grad in grads[1..].iter() {
+            adam.update(w.view_mut(), grad.view());
+            resumed.update(w_resumed.view_mut(), grad.view());
+        }
+        assert_eq!(w, w_resumed);
+    }
+
+    #[test]
+    #[should_panic(expected = "optimizer state shape does not match the parameter")]
+    fn wrong_shaped_state_is_rejected() {
+        let mut adam = Adam::<IxDyn>::new(0.01, 0.9, 0.999, 1e-8);
+        let state = vec![
+            ("m".to_string(), ArrayD::zeros(IxDyn(&[1]))),
+            ("v".to_string(), ArrayD::zeros(IxDyn(&[1]))),
+            ("iter".to_string(), arr0(1.0).into_dyn()),
+        ];
+        adam.load_state(&state).unwrap();
+        let mut w = arr2(&[[1.0, 2.0], [3.0, 4.0]]).into_dyn();
+        adam.update(w.view_mut(), ArrayD::ones(IxDyn(&[2, 2])).view());
+    }
+}
@@ -0,0 +1,57 @@
+use ndarray::{ArrayD, ArrayView, ArrayViewMut, Dimension};
+
+use super::{adam::Adam, optimize::Optimize};
+use crate::checkpoint::CheckpointError;
+
+/// 重み減衰を勾配に加えず、Adamによる更新とは別にパラメータへ直接適用するAdam
+pub struct AdamW<D: Dimension> {
+    learning_rate: f64,
+    weight_decay: f64,
+    adam: Adam<D>,
+}
+
+impl<D: Dimension> AdamW<D> {
+    pub fn new(learning_rate: f64, beta1: f64, beta2: f64, eps: f64, weight_decay: f64) -> Self {
+        Self {
+            learning_rate,
+            weight_decay,
+            adam: Adam::new(learning_rate, beta1, beta2, eps),
+        }
+    }
+}
+
+impl<D: Dimension> Optimize<D> for AdamW<D> {
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        let decay = self.learning_rate * self.weight_decay * &w;
+        self.adam.update(w.view_mut(), grad);
+        w -= &decay;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+        self.adam.set_learning_rate(learning_rate);
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        self.adam.state()
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        self.adam.load_state(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr1;
+
+    #[test]
+    fn decays_weights_independently_of_gradient() {
+        // 勾配が0ならAdamの更新量は0で、重み減衰だけが適用される
+        let mut adam_w = AdamW::new(0.1, 0.9, 0.999, 1e-8, 0.5);
+        let mut w = arr1(&[2.0, -4.0]);
+        adam_w.update(w.view_mut(), arr1(&[0.0, 0.0]).view());
+        assert_eq!(w, arr1(&[1.9, -3.8]));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::model::Model;
+
+/// 勾配爆発を防ぐための勾配のクリッピング
+#[derive(Clone, Copy, Debug)]
+pub enum GradientClipping {
+    /// 勾配の各要素を[-max, max]に収める
+    Value(f64),
+    /// 全パラメータの勾配をまとめた1つのベクトルとみなし、そのL2ノルムがmax以下になるよう一律に縮める
+    Norm(f64),
+}
+
+impl GradientClipping {
+    /// モデルが保持している勾配をクリッピングし、クリッピング前の勾配全体のL2ノルムを返す
+    pub fn apply(&self, model: &mut impl Model) -> f64 {
+        let norm = global_norm(model);
+        match *self {
+            GradientClipping::Value(max) => {
+                for mut param in model.parameters() {
+                    param.grad.mapv_inplace(|g| g.clamp(-max, max));
+                }
+            }
+            GradientClipping::Norm(max) => {
+                if norm > max {
+                    let scale = max / (norm + 1e-6);
+                    for mut param in model.parameters() {
+                        param.grad *= scale;
+                    }
+                }
+            }
+        }
+        norm
+    }
+}
+
+/// 全パラメータの勾配をまとめた1つのベクトルとみなしたときのL2ノルム
+pub fn global_norm(model: &mut impl Model) -> f64 {
+    model
+        .parameters()
+        .iter()
+        .map(|param| param.grad.fold(0.0, |acc, g| acc + g * g))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Parameter;
+    use ndarray::{arr1, Array1};
+
+    struct Grads {
+        a: Array1<f64>,
+        da: Array1<f64>,
+        b: Array1<f64>,
+        db: Array1<f64>,
+    }
+
+    impl Model for Grads {
+        fn parameters(&mut self) -> Vec<Parameter<'_>> {
+            vec![
+                Parameter::new(
+                    "a",
+                    self.a.view_mut().into_dyn(),
+                    self.da.view_mut().into_dyn(),
+                ),
+                Parameter::new(
+                    "b",
+                    self.b.view_mut().into_dyn(),
+                    self.db.view_mut().into_dyn(),
+                ),
+            ]
+        }
+    }
+
+    fn model() -> Grads {
+        // 勾配全体のL2ノルムは13
+        Grads {
+            a: arr1(&[0.0, 0.0]),
+            da: arr1(&[3.0, -4.0]),
+            b: arr1(&[0.0]),
+            db: arr1(&[12.0]),
+        }
+    }
+
+    #[test]
+    fn value_clamps_each_element() {
+        let mut model = model();
+        assert_eq!(GradientClipping::Value(3.5).apply(&mut model), 13.0);
+        assert_eq!(model.da, arr1(&[3.0, -3.5]));
+        assert_eq!(model.db, arr1(&[3.5]));
+    }
+
+    #[test]
+    fn norm_scales_all_gradients_together() {
+        let mut model = model();
+        assert_eq!(GradientClipping::Norm(6.5).apply(&mut model), 13.0);
+        assert!((global_norm(&mut model) - 6.5).abs() < 1e-6);
+        // 向きは変えない
+        assert!((model.da[0] / model.db[0] - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn norm_below_max_is_untouched() {
+        let mut model = model();
+        GradientClipping::Norm(20.0).apply(&mut model);
+        assert_eq!(model.da, arr1(&[3.0, -4.0]));
+        assert_eq!(model.db, arr1(&[12.0]));
+    }
+}
@@ -1,11 +1,12 @@
-use ndarray::{Array, Dimension};
+use ndarray::{Array, ArrayD, ArrayView, ArrayViewMut, Dimension};
 
-use super::optimize::Optimize;
+use super::optimize::{state_like, Optimize};
+use crate::checkpoint::{state_entry, CheckpointError};
 
 pub struct Momentum<D: Dimension> {
     learning_rate: f64,
     momentum: f64,
-    v: Array<f64, D>,
+    v: Option<Array<f64, D>>,
 }
 
 impl<D: Dimension> Momentum<D> {
@@ -13,19 +14,36 @@ impl<D: Dimension> Momentum<D> {
         Self {
             learning_rate,
             momentum,
-            v: Array::zeros(D::default()),
+            v: None,
         }
     }
 }
 
 impl<D: Dimension> Optimize<D> for Momentum<D> {
-    fn update(&mut self, w: &mut Array<f64, D>, grad: &Array<f64, D>) {
-        // 初めての呼び出し時vのdimは(0,0,...)なので、wの形に揃える
-        if self.v.len() == 0 {
-            self.v = Array::zeros(w.raw_dim());
-        }
-        let v = self.momentum * &self.v - self.learning_rate * grad;
-        *w += &v;
-        self.v = v;
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        let v = state_like(&mut self.v, &w);
+        *v = self.momentum * &*v - self.learning_rate * &grad;
+        w += &*v;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        self.v
+            .iter()
+            .map(|v| ("v".to_string(), v.clone().into_dyn()))
+            .collect()
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        // 一度も更新していない状態は空で保存される
+        self.v = if state.is_empty() {
+            None
+        } else {
+            Some(state_entry(state, "v")?)
+        };
+        Ok(())
     }
 }
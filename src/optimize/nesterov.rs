@@ -0,0 +1,80 @@
+use ndarray::{Array, ArrayD, ArrayView, ArrayViewMut, Dimension};
+
+use super::optimize::{state_like, Optimize};
+use crate::checkpoint::{state_entry, CheckpointError};
+
+/// Nesterovの加速勾配法
+///
+/// Momentumの速度で一歩進んだ位置での勾配を用いる更新 v = μv - η∇L(w + μv), w += v を、
+/// 一歩進んだ位置をパラメータとして保持することで、現在の位置での勾配だけで書き直したもの
+pub struct Nesterov<D: Dimension> {
+    learning_rate: f64,
+    momentum: f64,
+    v: Option<Array<f64, D>>,
+}
+
+impl<D: Dimension> Nesterov<D> {
+    pub fn new(learning_rate: f64, momentum: f64) -> Self {
+        Self {
+            learning_rate,
+            momentum,
+            v: None,
+        }
+    }
+}
+
+impl<D: Dimension> Optimize<D> for Nesterov<D> {
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        let v = state_like(&mut self.v, &w);
+        // 更新前の速度を用いて w += μ^2 v - (1 + μ)η∇L とし、その後で速度を更新する
+        w += &(self.momentum * self.momentum * &*v);
+        w -= &((1.0 + self.momentum) * self.learning_rate * &grad);
+        *v = self.momentum * &*v - self.learning_rate * &grad;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        self.v
+            .iter()
+            .map(|v| ("v".to_string(), v.clone().into_dyn()))
+            .collect()
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        // 一度も更新していない状態は空で保存される
+        self.v = if state.is_empty() {
+            None
+        } else {
+            Some(state_entry(state, "v")?)
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, Ix1};
+
+    #[test]
+    fn matches_look_ahead_form() {
+        // L(w) = a * w^2 / 2 の勾配は a * w
+        let (a, learning_rate, momentum) = (2.0, 0.1, 0.9);
+        let mut nesterov = Nesterov::<Ix1>::new(learning_rate, momentum);
+        let mut w = arr1(&[1.0]);
+        // 一歩進んだ位置の勾配を用いる元の形
+        let (mut w_ahead, mut v) = (1.0, 0.0);
+        for _ in 0..5 {
+            let grad = a * &w;
+            nesterov.update(w.view_mut(), grad.view());
+            v = momentum * v - learning_rate * a * (w_ahead + momentum * v);
+            w_ahead += v;
+            // 書き直した形のパラメータは、元の形で一歩進んだ位置 w + μv にあたる
+            let expected = w_ahead + momentum * v;
+            assert!((w[0] - expected).abs() < 1e-12, "{} != {}", w[0], expected);
+        }
+    }
+}
@@ -1,5 +1,35 @@
-use ndarray::{Array, Dimension};
+use ndarray::{Array, ArrayD, ArrayView, ArrayViewMut, Dimension};
+
+use crate::checkpoint::CheckpointError;
 
 pub trait Optimize<D: Dimension> {
-    fn update(&mut self, w: &mut Array<f64, D>, grad: &Array<f64, D>);
+    fn update(&mut self, w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>);
+    /// 次のupdateで用いる学習率
+    fn learning_rate(&self) -> f64;
+    /// 学習率スケジューラなどから、次のupdate以降に用いる学習率を設定する
+    fn set_learning_rate(&mut self, learning_rate: f64);
+    /// チェックポイントに保存する内部状態。内部状態を持たない場合は空
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        Vec::new()
+    }
+    /// stateで得た内部状態を復元する
+    fn load_state(&mut self, _state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        Ok(())
+    }
+}
+
+/// パラメータごとの内部状態を、初めての呼び出し時にwと同じ形の0で初期化して返す
+///
+/// 読み込んだ内部状態の形がwと異なる場合は、ブロードキャストして更新を続けないようpanicする
+pub fn state_like<'a, D: Dimension>(
+    state: &'a mut Option<Array<f64, D>>,
+    w: &ArrayViewMut<f64, D>,
+) -> &'a mut Array<f64, D> {
+    let state = state.get_or_insert_with(|| Array::zeros(w.raw_dim()));
+    assert_eq!(
+        state.shape(),
+        w.shape(),
+        "optimizer state shape does not match the parameter"
+    );
+    state
 }
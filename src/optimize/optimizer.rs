@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use ndarray::{ArrayD, IxDyn};
+
+use crate::{
+    checkpoint::{CheckpointError, NamedArrays},
+    lr_scheduler::lr_scheduler::LrScheduler,
+    model::Model,
+    regularization::Regularization,
+};
+
+use super::{gradient_clipping::GradientClipping, optimize::Optimize};
+
+/// モデルの全てのパラメータを更新する
+///
+/// パラメータごとに`factory`で作ったOptimizeを持つため、MomentumやAdaGradの内部状態もパラメータごとに保持される。
+/// 学習率スケジューラを設定した場合は、stepのたびにスケジューラの学習率を読み出して用い、スケジューラを1ステップ進める。
+/// 勾配のクリッピングを設定した場合は、パラメータを更新する前に勾配をクリッピングする。
+/// 重み減衰を設定した場合は、勾配による更新の後に、パラメータを学習率に比例して直接減衰させる(decoupled weight decay)。
+///
+/// # Examples
+/// ```ignore
+///     let mut optimizer = Optimizer::new(move || Momentum::new(learning_rate, 0.9))
+///         .with_scheduler(StepDecay::new(learning_rate, 1000, 0.5))
+///         .with_gradient_clipping(GradientClipping::Norm(5.0))
+///         .with_weight_decay(Regularization::new(Penalty::L2(1e-4)).exclude(&["b"]));
+///     network.gradient(&x_batch, &t_batch);
+///     optimizer.step(&mut network);
+/// ```
+pub struct Optimizer<O: Optimize<IxDyn>> {
+    factory: Box<dyn Fn() -> O>,
+    states: HashMap<String, O>,
+    scheduler: Option<Box<dyn LrScheduler>>,
+    clipping: Option<GradientClipping>,
+    weight_decay: Option<Regularization>,
+    last_grad_norm: Option<f64>,
+}
+
+impl<O: Optimize<IxDyn>> Optimizer<O> {
+    pub fn new(factory: impl Fn() -> O + 'static) -> Self {
+        Self {
+            factory: Box::new(factory),
+            states: HashMap::new(),
+            scheduler: None,
+            clipping: None,
+            weight_decay: None,
+            last_grad_norm: None,
+        }
+    }
+    pub fn with_scheduler(mut self, scheduler: impl LrScheduler + 'static) -> Self {
+        self.scheduler = Some(Box::new(scheduler));
+        self
+    }
+    pub fn with_gradient_clipping(mut self, clipping: GradientClipping) -> Self {
+        self.clipping = Some(clipping);
+        self
+    }
+    pub fn with_weight_decay(mut self, weight_decay: Regularization) -> Self {
+        self.weight_decay = Some(weight_decay);
+        self
+    }
+    /// 直前のstepでのクリッピング前の勾配全体のL2ノルム。クリッピングを設定していない場合はNone
+    pub fn last_grad_norm(&self) -> Option<f64> {
+        self.last_grad_norm
+    }
+    pub fn scheduler_mut(&mut self) -> Option<&mut (dyn LrScheduler + 'static)> {
+        self.scheduler.as_deref_mut()
+    }
+    /// 次のstepで用いられる学習率。スケジューラを設定していない場合はNone
+    pub fn learning_rate(&self) -> Option<f64> {
+        self.scheduler
+            .as_ref()
+            .map(|scheduler| scheduler.learning_rate())
+    }
+    pub fn step(&mut self, model: &mut impl Model) {
+        self.last_grad_norm = self.clipping.map(|clipping| clipping.apply(model));
+        let learning_rate = self.learning_rate();
+        for param in model.parameters() {
+            let weight_decay = self
+                .weight_decay
+                .as_ref()
+                .filter(|weight_decay| !weight_decay.is_excluded(&param.name));
+            let optimize = self
+                .states
+                .entry(param.name)
+                .or_insert_with(|| (self.factory)());
+            if let Some(learning_rate) = learning_rate {
+                optimize.set_learning_rate(learning_rate);
+            }
+            let mut value = param.value;
+            optimize.update(value.view_mut(), param.grad.view());
+            if let Some(weight_decay) = weight_decay {
+                weight_decay.decay(value, optimize.learning_rate());
+            }
+        }
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.step();
+        }
+    }
+    /// パラメータ名ごとの内部状態を、パラメータ名の順に返す
+    pub fn state(&self) -> Vec<(String, NamedArrays)> {
+        let mut states = self
+            .states
+            .iter()
+            .map(|(name, optimize)| (name.clone(), optimize.state()))
+            .collect::<Vec<_>>();
+        states.sort_by(|x, y| x.0.cmp(&y.0));
+        states
+    }
+    /// 学習率スケジューラの状態。スケジューラを設定していない場合は空
+    pub fn scheduler_state(&self) -> NamedArrays {
+        self.scheduler
+            .as_ref()
+            .map_or(Vec::new(), |scheduler| scheduler.state())
+    }
+    /// stateとscheduler_stateで得た状態を復元する。それまでの内部状態は捨てられる
+    ///
+    /// エラーの場合は、パラメータごとの内部状態を変更しない
+    pub fn load_state(
+        &mut self,
+        states: &[(String, NamedArrays)],
+        scheduler_state: &[(String, ArrayD<f64>)],
+    ) -> Result<(), CheckpointError> {
+        let mut loaded = HashMap::new();
+        for (name, state) in states.iter() {
+            let mut optimize = (self.factory)();
+            optimize.load_state(state)?;
+            loaded.insert(name.clone(), optimize);
+        }
+        match self.scheduler.as_mut() {
+            Some(scheduler) => scheduler.load_state(scheduler_state)?,
+            None => {
+                if let Some((name, _)) = scheduler_state.first() {
+                    return Err(CheckpointError::UnexpectedEntry(name.clone()));
+                }
+            }
+        }
+        self.states = loaded;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lr_scheduler::step_decay::StepDecay,
+        model::Parameter,
+        optimize::{momentum::Momentum, sgd::SGD},
+        regularization::Penalty,
+    };
+    use ndarray::{arr1, Array1};
+
+    struct TwoParams {
+        a: Array1<f64>,
+        da: Array1<f64>,
+        b: Array1<f64>,
+        db: Array1<f64>,
+    }
+
+    impl Model for TwoParams {
+        fn parameters(&mut self) -> Vec<Parameter<'_>> {
+            vec![
+                Parameter::new(
+                    "a",
+                    self.a.view_mut().into_dyn(),
+                    self.da.view_mut().into_dyn(),
+                ),
+                Parameter::new(
+                    "b",
+                    self.b.view_mut().into_dyn(),
+                    self.db.view_mut().into_dyn(),
+                ),
+            ]
+        }
+    }
+
+    #[test]
+    fn keeps_separate_state_for_each_parameter() {
+        let mut model = TwoParams {
+            a: arr1(&[1.0, 2.0]),
+            da: arr1(&[1.0, -1.0]),
+            b: arr1(&[0.0]),
+            db: arr1(&[2.0]),
+        };
+        let mut optimizer = Optimizer::new(|| Momentum::new(0.1, 0.5));
+        optimizer.step(&mut model);
+        optimizer.step(&mut model);
+        // 各パラメータの速度は v1 = -0.1g, v2 = 0.5 * v1 - 0.1g = -0.15g
+        assert!((&model.a - &arr1(&[0.75, 2.25]))
+            .iter()
+            .all(|d| d.abs() < 1e-12));
+        assert!((&model.b - &arr1(&[-0.5])).iter().all(|d| d.abs() < 1e-12));
+        let names = optimizer
+            .state()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn follows_scheduler_learning_rate() {
+        let mut model = TwoParams {
+            a: arr1(&[0.0]),
+            da: arr1(&[1.0]),
+            b: arr1(&[0.0]),
+            db: arr1(&[0.0]),
+        };
+        let mut optimizer =
+            Optimizer::new(|| SGD::new(1.0)).with_scheduler(StepDecay::new(0.5, 1, 0.5));
+        for _ in 0..3 {
+            optimizer.step(&mut model);
+        }
+        assert_eq!(model.a, arr1(&[-0.875]));
+        assert_eq!(optimizer.learning_rate(), Some(0.0625));
+    }
+
+    #[test]
+    fn weight_decay_is_applied_after_update_except_excluded() {
+        let mut model = TwoParams {
+            a: arr1(&[2.0]),
+            da: arr1(&[1.0]),
+            b: arr1(&[2.0]),
+            db: arr1(&[1.0]),
+        };
+        let mut optimizer = Optimizer::new(|| SGD::new(0.5))
+            .with_weight_decay(Regularization::new(Penalty::L2(0.2)).exclude(&["b"]));
+        optimizer.step(&mut model);
+        // a: (2 - 0.5) * (1 - 0.5 * 0.2)
+        assert!((model.a[0] - 1.35).abs() < 1e-12);
+        assert_eq!(model.b, arr1(&[1.5]));
+    }
+}
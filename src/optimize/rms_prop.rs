@@ -0,0 +1,75 @@
+use ndarray::{Array, ArrayD, ArrayView, ArrayViewMut, Dimension};
+
+use super::optimize::{state_like, Optimize};
+use crate::checkpoint::{state_entry, CheckpointError};
+
+pub struct RMSProp<D: Dimension> {
+    learning_rate: f64,
+    decay_rate: f64,
+    h: Option<Array<f64, D>>,
+}
+
+impl<D: Dimension> RMSProp<D> {
+    pub fn new(learning_rate: f64, decay_rate: f64) -> Self {
+        Self {
+            learning_rate,
+            decay_rate,
+            h: None,
+        }
+    }
+}
+
+impl<D: Dimension> Optimize<D> for RMSProp<D> {
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        let h = state_like(&mut self.h, &w);
+        // AdaGradと異なり過去の勾配を指数的に忘れるため、学習率が0に近づき続けることはない
+        *h = self.decay_rate * &*h + (1.0 - self.decay_rate) * &(&grad * &grad);
+        let h_sqrt = h.map(|x| x.sqrt() + 1e-7);
+        w -= &(&grad * self.learning_rate / h_sqrt);
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    fn state(&self) -> Vec<(String, ArrayD<f64>)> {
+        self.h
+            .iter()
+            .map(|h| ("h".to_string(), h.clone().into_dyn()))
+            .collect()
+    }
+    fn load_state(&mut self, state: &[(String, ArrayD<f64>)]) -> Result<(), CheckpointError> {
+        // 一度も更新していない状態は空で保存される
+        self.h = if state.is_empty() {
+            None
+        } else {
+            Some(state_entry(state, "h")?)
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, Ix1};
+
+    #[test]
+    fn step_size_does_not_vanish_under_constant_gradient() {
+        // AdaGradでは更新量が1/sqrt(t)で小さくなり続けるが、RMSPropでは学習率に近づく
+        let mut rms_prop = RMSProp::<Ix1>::new(0.01, 0.9);
+        let mut w = arr1(&[0.0]);
+        let grad = arr1(&[3.0]);
+        for _ in 0..100 {
+            rms_prop.update(w.view_mut(), grad.view());
+        }
+        let before = w[0];
+        rms_prop.update(w.view_mut(), grad.view());
+        assert!(
+            (before - w[0] - 0.01).abs() < 1e-6,
+            "step: {}",
+            before - w[0]
+        );
+    }
+}
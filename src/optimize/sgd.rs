@@ -1,9 +1,10 @@
 use std::marker::PhantomData;
 
-use ndarray::{Array, Dimension};
+use ndarray::{ArrayView, ArrayViewMut, Dimension};
 
 use super::optimize::Optimize;
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct SGD<D: Dimension> {
     learning_rate: f64,
     d: PhantomData<D>,
@@ -19,7 +20,13 @@ impl<D: Dimension> SGD<D> {
 }
 
 impl<D: Dimension> Optimize<D> for SGD<D> {
-    fn update(&mut self, w: &mut Array<f64, D>, grad: &Array<f64, D>) {
-        *w -= &(grad * self.learning_rate);
+    fn update(&mut self, mut w: ArrayViewMut<f64, D>, grad: ArrayView<f64, D>) {
+        w -= &(&grad * self.learning_rate);
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
     }
 }
@@ -0,0 +1,3 @@
+pub mod line_chart;
+pub mod plot_error;
+pub mod training_curves;
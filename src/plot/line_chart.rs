@@ -0,0 +1,143 @@
+use std::{error::Error, path::Path};
+
+use plotters::{coord::Shift, prelude::*};
+
+use super::plot_error::PlotError;
+
+/// 凡例に表示する名前のついた折れ線
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Series {
+    pub fn new(label: impl Into<String>, points: Vec<(f64, f64)>) -> Self {
+        Series {
+            label: label.into(),
+            points,
+        }
+    }
+}
+
+/// 複数の折れ線を重ねたグラフ
+pub struct LineChart {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub series: Vec<Series>,
+    /// 指定しない場合は、全ての点が収まる範囲にする
+    pub y_range: Option<(f64, f64)>,
+    /// 画像の大きさ(ピクセル)
+    pub size: (u32, u32),
+}
+
+impl LineChart {
+    pub fn new(
+        title: impl Into<String>,
+        x_label: impl Into<String>,
+        y_label: impl Into<String>,
+    ) -> Self {
+        LineChart {
+            title: title.into(),
+            x_label: x_label.into(),
+            y_label: y_label.into(),
+            series: Vec::new(),
+            y_range: None,
+            size: (800, 600),
+        }
+    }
+    pub fn with_series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+    pub fn with_y_range(mut self, min: f64, max: f64) -> Self {
+        self.y_range = Some((min, max));
+        self
+    }
+    /// グラフを画像として書き出す。形式は拡張子(.pngまたは.svg)で選ぶ
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PlotError> {
+        let path = path.as_ref();
+        let ranges = self.ranges().ok_or(PlotError::NoData)?;
+        let result = match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => self.draw(
+                BitMapBackend::new(path, self.size).into_drawing_area(),
+                ranges,
+            ),
+            Some("svg") => self.draw(SVGBackend::new(path, self.size).into_drawing_area(), ranges),
+            _ => return Err(PlotError::UnsupportedFormat(path.to_path_buf())),
+        };
+        result.map_err(|e| PlotError::Drawing(e.to_string()))
+    }
+    /// (x軸の範囲, y軸の範囲)。点が1つもない場合はNone
+    fn ranges(&self) -> Option<((f64, f64), (f64, f64))> {
+        let points = || self.series.iter().flat_map(|s| s.points.iter());
+        let x_range = bounds(points().map(|p| p.0))?;
+        let y_range = match self.y_range {
+            Some(range) => range,
+            None => {
+                let (min, max) = bounds(points().map(|p| p.1))?;
+                // 上下に少し余白を空ける
+                let margin = (max - min) * 0.05;
+                (min - margin, max + margin)
+            }
+        };
+        Some((x_range, y_range))
+    }
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+        ((x_min, x_max), (y_min, y_max)): ((f64, f64), (f64, f64)),
+    ) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(&self.title, ("sans-serif", 24))
+            .margin(15)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+        chart
+            .configure_mesh()
+            .x_desc(&self.x_label)
+            .y_desc(&self.y_label)
+            .draw()?;
+        for (i, series) in self.series.iter().enumerate() {
+            let color = Palette99::pick(i).to_rgba();
+            chart
+                .draw_series(
+                    LineSeries::new(series.points.iter().cloned(), color.stroke_width(2))
+                        // 検証結果のように点がまばらな場合も見えるようにする
+                        .point_size(if series.points.len() < 50 { 3 } else { 0 }),
+                )?
+                .label(&series.label)
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+                });
+        }
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// 最小値と最大値。全て同じ値の場合は、範囲が空にならないよう広げる
+fn bounds(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    let (min, max) =
+        values
+            .filter(|v| v.is_finite())
+            .fold(None, |acc: Option<(f64, f64)>, v| match acc {
+                None => Some((v, v)),
+                Some((min, max)) => Some((min.min(v), max.max(v))),
+            })?;
+    if min == max {
+        Some((min - 0.5, max + 0.5))
+    } else {
+        Some((min, max))
+    }
+}
@@ -0,0 +1,27 @@
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum PlotError {
+    /// 拡張子が.png、.svgのどちらでもない
+    UnsupportedFormat(PathBuf),
+    /// 描く点が1つもない
+    NoData,
+    /// plottersでの描画、書き出しに失敗した
+    Drawing(String),
+}
+
+impl fmt::Display for PlotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlotError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported image format for {}, expected .png or .svg",
+                path.display()
+            ),
+            PlotError::NoData => write!(f, "no data to plot"),
+            PlotError::Drawing(message) => write!(f, "failed to draw chart: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PlotError {}
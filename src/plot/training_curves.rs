@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use super::{
+    line_chart::{LineChart, Series},
+    plot_error::PlotError,
+};
+use crate::trainer::history::History;
+
+/// 学習曲線の横軸
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XAxis {
+    /// 訓練データはミニバッチごとの値を描く
+    Iteration,
+    /// 訓練データはエポックごとの平均を描く
+    Epoch,
+}
+
+/// 訓練データと検証データの損失の推移をグラフにする
+pub fn loss_chart(history: &History, x_axis: XAxis) -> LineChart {
+    let (train, validation) = match x_axis {
+        XAxis::Iteration => (
+            by_iteration(&history.batch_loss),
+            history
+                .validation
+                .iter()
+                .map(|v| (v.iteration as f64, v.loss))
+                .collect(),
+        ),
+        XAxis::Epoch => (
+            by_iteration(&history.epoch_loss),
+            history
+                .validation
+                .iter()
+                .map(|v| (v.epoch as f64, v.loss))
+                .collect(),
+        ),
+    };
+    chart("loss", "loss", x_axis, train, validation)
+}
+
+/// 訓練データと検証データの認識精度の推移をグラフにする
+pub fn accuracy_chart(history: &History, x_axis: XAxis) -> LineChart {
+    let (train, validation) = match x_axis {
+        XAxis::Iteration => (
+            by_iteration(&history.batch_accuracy),
+            history
+                .validation
+                .iter()
+                .map(|v| (v.iteration as f64, v.accuracy))
+                .collect(),
+        ),
+        XAxis::Epoch => (
+            by_iteration(&history.epoch_accuracy),
+            history
+                .validation
+                .iter()
+                .map(|v| (v.epoch as f64, v.accuracy))
+                .collect(),
+        ),
+    };
+    chart("accuracy", "accuracy", x_axis, train, validation).with_y_range(0.0, 1.0)
+}
+
+/// 損失と認識精度のグラフを、dirに"{名前}_{iteration|epoch}.{png|svg}"としてPNGとSVGの両方で書き出す
+///
+/// # Examples
+/// ```no_run
+/// # use zero_deeplearning::{
+/// #     plot::{plot_error::PlotError, training_curves::save_training_curves},
+/// #     trainer::history::History,
+/// # };
+/// # fn main() -> Result<(), PlotError> {
+/// # let history = History::default();
+/// save_training_curves(&history, "images")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn save_training_curves(history: &History, dir: impl AsRef<Path>) -> Result<(), PlotError> {
+    let dir = dir.as_ref();
+    for (x_axis, suffix) in [(XAxis::Iteration, "iteration"), (XAxis::Epoch, "epoch")] {
+        for (name, chart) in [
+            ("loss", loss_chart(history, x_axis)),
+            ("accuracy", accuracy_chart(history, x_axis)),
+        ] {
+            for extension in ["png", "svg"] {
+                chart.save(dir.join(format!("{}_{}.{}", name, suffix, extension)))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// i番目の値を、i+1回目(イテレーション、エポック)の値として並べる
+fn by_iteration(values: &[f64]) -> Vec<(f64, f64)> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| ((i + 1) as f64, v))
+        .collect()
+}
+
+fn chart(
+    title: &str,
+    y_label: &str,
+    x_axis: XAxis,
+    train: Vec<(f64, f64)>,
+    validation: Vec<(f64, f64)>,
+) -> LineChart {
+    let x_label = match x_axis {
+        XAxis::Iteration => "iteration",
+        XAxis::Epoch => "epoch",
+    };
+    let mut chart =
+        LineChart::new(title, x_label, y_label).with_series(Series::new("train", train));
+    if !validation.is_empty() {
+        chart = chart.with_series(Series::new("validation", validation));
+    }
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trainer::history::Evaluation;
+
+    fn history() -> History {
+        let evaluation = |epoch, iteration, loss, accuracy| Evaluation {
+            epoch,
+            iteration,
+            loss,
+            accuracy,
+        };
+        History {
+            batch_loss: vec![2.0, 1.5, 1.2, 1.0],
+            batch_accuracy: vec![0.2, 0.4, 0.5, 0.7],
+            epoch_loss: vec![1.75, 1.1],
+            epoch_accuracy: vec![0.3, 0.6],
+            validation: vec![evaluation(1, 2, 1.6, 0.35), evaluation(2, 4, 1.3, 0.55)],
+        }
+    }
+
+    #[test]
+    fn places_training_and_validation_points() {
+        let chart = loss_chart(&history(), XAxis::Iteration);
+        assert_eq!(chart.x_label, "iteration");
+        assert_eq!(
+            chart.series[0].points,
+            vec![(1.0, 2.0), (2.0, 1.5), (3.0, 1.2), (4.0, 1.0)]
+        );
+        assert_eq!(chart.series[1].points, vec![(2.0, 1.6), (4.0, 1.3)]);
+
+        let chart = accuracy_chart(&history(), XAxis::Epoch);
+        assert_eq!(chart.series[0].points, vec![(1.0, 0.3), (2.0, 0.6)]);
+        assert_eq!(chart.series[1].points, vec![(1.0, 0.35), (2.0, 0.55)]);
+        assert_eq!(chart.y_range, Some((0.0, 1.0)));
+
+        // 検証していない場合は、訓練データの折れ線だけを描く
+        let history = History {
+            validation: Vec::new(),
+            ..history()
+        };
+        assert_eq!(loss_chart(&history, XAxis::Epoch).series.len(), 1);
+    }
+
+    #[test]
+    fn saves_svg_and_rejects_unknown_formats() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("{}_loss.svg", std::process::id()));
+        loss_chart(&history(), XAxis::Epoch).save(&path).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("validation"));
+
+        assert!(matches!(
+            loss_chart(&history(), XAxis::Epoch).save(dir.join("loss.jpg")),
+            Err(PlotError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            loss_chart(&History::default(), XAxis::Epoch).save(dir.join("loss.svg")),
+            Err(PlotError::NoData)
+        ));
+    }
+}
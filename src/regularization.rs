@@ -0,0 +1,179 @@
+use ndarray::{ArrayViewD, ArrayViewMutD};
+
+use crate::model::Model;
+
+#[derive(Clone, Debug)]
+pub enum Penalty {
+    /// 0.5 * λ * Σw^2
+    L2(f64),
+    /// λ * Σ|w|
+    L1(f64),
+    /// λ1 * Σ|w| + 0.5 * λ2 * Σw^2
+    ElasticNet { l1: f64, l2: f64 },
+}
+
+/// モデルのパラメータに対する正則化
+///
+/// `exclude`に指定した名前のパラメータには正則化を行わない。
+/// 名前は完全一致に加え、"."以降の部分との一致も見るため、Sequentialでは"b"で全ての層のバイアスを除外できる。
+///
+/// # Examples
+/// ```
+/// use ndarray_rand::rand_distr::Normal;
+/// use zero_deeplearning::{
+///     regularization::{Penalty, Regularization},
+///     two_layer_net::TwoLayerNet,
+/// };
+///
+/// let mut network = TwoLayerNet::new(4, 3, 2, &Normal::new(0.0, 0.1).unwrap());
+/// network.regularization =
+///     Some(Regularization::new(Penalty::L2(1e-4)).exclude(&["b1", "b2", "batch_aff"]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Regularization {
+    penalty: Penalty,
+    exclude: Vec<String>,
+}
+
+impl Regularization {
+    pub fn new(penalty: Penalty) -> Self {
+        Self {
+            penalty,
+            exclude: Vec::new(),
+        }
+    }
+    pub fn exclude(mut self, names: &[&str]) -> Self {
+        self.exclude
+            .extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+    /// 名前がnameのパラメータを正則化の対象から除外するか
+    pub fn is_excluded(&self, name: &str) -> bool {
+        self.exclude.iter().any(|excluded| {
+            name == excluded
+                || name
+                    .rsplit_once('.')
+                    .is_some_and(|(_, suffix)| suffix == excluded)
+        })
+    }
+    fn penalty_of(&self, w: ArrayViewD<f64>) -> f64 {
+        let l1 = || w.fold(0.0, |acc, x| acc + x.abs());
+        let l2 = || 0.5 * w.fold(0.0, |acc, x| acc + x * x);
+        match self.penalty {
+            Penalty::L2(lambda) => lambda * l2(),
+            Penalty::L1(lambda) => lambda * l1(),
+            Penalty::ElasticNet {
+                l1: lambda1,
+                l2: lambda2,
+            } => lambda1 * l1() + lambda2 * l2(),
+        }
+    }
+    /// `out`に、正則化項のwについての勾配の`scale`倍を加える
+    fn add_gradient(&self, w: ArrayViewD<f64>, mut out: ArrayViewMutD<f64>, scale: f64) {
+        // signumは0に対して1を返すため、0での劣勾配として0を用いる
+        let sign = |x: f64| if x == 0.0 { 0.0 } else { x.signum() };
+        match self.penalty {
+            Penalty::L2(lambda) => out.scaled_add(scale * lambda, &w),
+            Penalty::L1(lambda) => out.scaled_add(scale * lambda, &w.mapv(sign)),
+            Penalty::ElasticNet {
+                l1: lambda1,
+                l2: lambda2,
+            } => {
+                out.scaled_add(scale * lambda1, &w.mapv(sign));
+                out.scaled_add(scale * lambda2, &w);
+            }
+        }
+    }
+    /// 損失に加える正則化項
+    pub fn penalty(&self, model: &mut impl Model) -> f64 {
+        model
+            .parameters()
+            .iter()
+            .filter(|param| !self.is_excluded(&param.name))
+            .map(|param| self.penalty_of(param.value.view()))
+            .sum()
+    }
+    /// 正則化項の勾配を、モデルが保持している勾配に加える
+    pub fn apply(&self, model: &mut impl Model) {
+        for param in model.parameters() {
+            if !self.is_excluded(&param.name) {
+                self.add_gradient(param.value.view(), param.grad, 1.0);
+            }
+        }
+    }
+    /// 勾配を介さずに、パラメータwを直接`learning_rate`に比例して減衰させる(decoupled weight decay)
+    ///
+    /// 除外するかどうかは呼び出し側がis_excludedで判断する。Optimizer::with_weight_decayから用いる
+    pub fn decay(&self, w: ArrayViewMutD<f64>, learning_rate: f64) {
+        let w_old = w.to_owned();
+        self.add_gradient(w_old.view(), w, -learning_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Parameter;
+    use ndarray::{arr1, Array1};
+
+    /// Sequentialと同じく"層の番号.名前"の名前を持つモデル
+    struct Layers {
+        w: Array1<f64>,
+        dw: Array1<f64>,
+        b: Array1<f64>,
+        db: Array1<f64>,
+    }
+
+    impl Model for Layers {
+        fn parameters(&mut self) -> Vec<Parameter<'_>> {
+            vec![
+                Parameter::new(
+                    "0.w",
+                    self.w.view_mut().into_dyn(),
+                    self.dw.view_mut().into_dyn(),
+                ),
+                Parameter::new(
+                    "0.b",
+                    self.b.view_mut().into_dyn(),
+                    self.db.view_mut().into_dyn(),
+                ),
+            ]
+        }
+    }
+
+    fn model() -> Layers {
+        Layers {
+            w: arr1(&[1.0, -2.0, 0.0]),
+            dw: arr1(&[0.0, 0.0, 0.0]),
+            b: arr1(&[3.0]),
+            db: arr1(&[0.0]),
+        }
+    }
+
+    #[test]
+    fn penalty_skips_excluded_suffix() {
+        let mut model = model();
+        let l2 = Regularization::new(Penalty::L2(0.1)).exclude(&["b"]);
+        assert!((l2.penalty(&mut model) - 0.25).abs() < 1e-12);
+        let elastic_net = Regularization::new(Penalty::ElasticNet { l1: 0.1, l2: 0.1 });
+        assert!((elastic_net.penalty(&mut model) - (0.3 + 0.25 + 0.3 + 0.45)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn apply_adds_gradient_of_penalty() {
+        let mut model = model();
+        Regularization::new(Penalty::L1(0.5))
+            .exclude(&["b"])
+            .apply(&mut model);
+        // 0での劣勾配は0とする
+        assert_eq!(model.dw, arr1(&[0.5, -0.5, 0.0]));
+        assert_eq!(model.db, arr1(&[0.0]));
+    }
+
+    #[test]
+    fn decay_shrinks_weights_in_proportion_to_learning_rate() {
+        let mut w = arr1(&[1.0, -2.0]);
+        Regularization::new(Penalty::L2(0.5)).decay(w.view_mut().into_dyn(), 0.1);
+        assert!((&w - &arr1(&[0.95, -1.9])).iter().all(|d| d.abs() < 1e-12));
+    }
+}
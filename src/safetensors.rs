@@ -0,0 +1,4 @@
+pub mod json;
+#[allow(clippy::module_inception)]
+pub mod safetensors;
+pub mod safetensors_error;
@@ -0,0 +1,301 @@
+/// safetensorsのヘッダを読むための最小限のJSON
+#[derive(Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// 整数を精度を落とさずに読めるよう、数値は文字列のまま持つ
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(format!("unexpected trailing characters at {}", parser.pos));
+        }
+        Ok(value)
+    }
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// JSONの文字列リテラルとして書き出す
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at {}", c, self.pos))
+        }
+    }
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                return Err(format!("invalid literal at {}", self.pos));
+            }
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(format!("unexpected character at {}", self.pos)),
+        }
+    }
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(entries));
+                }
+                _ => return Err(format!("expected `,` or `}}` at {}", self.pos)),
+            }
+        }
+    }
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(format!("expected `,` or `]` at {}", self.pos)),
+            }
+        }
+    }
+    fn string(&mut self) -> Result<String, String> {
+        if self.peek() != Some('"') {
+            return Err(format!("expected string at {}", self.pos));
+        }
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let c = self.peek().ok_or("unterminated string")?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = self.peek().ok_or("unterminated string")?;
+                    self.pos += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => s.push(escaped),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let hex = self
+                                .chars
+                                .get(self.pos..self.pos + 4)
+                                .ok_or("invalid unicode escape")?
+                                .iter()
+                                .collect::<String>();
+                            self.pos += 4;
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| "invalid unicode escape")?;
+                            s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(format!("invalid escape at {}", self.pos)),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+    /// -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)? の形の数値を読む
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some('0') => self.pos += 1,
+            Some('1'..='9') => self.digits(),
+            _ => return Err(format!("invalid number at {}", start)),
+        }
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            if !matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                return Err(format!("invalid number at {}", start));
+            }
+            self.digits();
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            if !matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                return Err(format!("invalid number at {}", start));
+            }
+            self.digits();
+        }
+        Ok(Json::Number(self.chars[start..self.pos].iter().collect()))
+    }
+    fn digits(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_values() {
+        let json = Json::parse(
+            r#" {"a": [1, -2.5e3, 0], "b\n\u00e9": "x\"y", "c": {"d": true, "e": null}, "f": []} "#,
+        )
+        .unwrap();
+        let a = json.get("a").and_then(Json::as_array).unwrap();
+        assert_eq!(a[0].as_u64(), Some(1));
+        assert!(matches!(&a[1], Json::Number(n) if n == "-2.5e3"));
+        assert_eq!(a[2].as_u64(), Some(0));
+        assert_eq!(json.get("b\n\u{e9}").and_then(Json::as_str), Some("x\"y"));
+        assert!(matches!(
+            json.get("c").and_then(|c| c.get("d")),
+            Some(Json::Bool(true))
+        ));
+        assert!(matches!(
+            json.get("c").and_then(|c| c.get("e")),
+            Some(Json::Null)
+        ));
+        assert_eq!(
+            json.get("f").and_then(Json::as_array).map(<[Json]>::len),
+            Some(0)
+        );
+        assert!(json.get("g").is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        for text in [
+            "-", "1e", "1.", "1.e5", "01", "-a", "1e+", "+1", ".5", "1-2",
+        ] {
+            assert!(Json::parse(text).is_err(), "`{}` was accepted", text);
+        }
+        for text in ["0", "-0", "10", "1.5", "1e5", "1E-5", "-0.25e+2"] {
+            assert!(Json::parse(text).is_ok(), "`{}` was rejected", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for text in [
+            "",
+            "{",
+            "[1,]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "\"abc",
+            "tru",
+            "[1] 2",
+            "{1: 2}",
+        ] {
+            assert!(Json::parse(text).is_err(), "`{}` was accepted", text);
+        }
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        let s = "a\"b\\c\nd\te\u{1}é";
+        assert_eq!(Json::parse(&quote(s)).unwrap().as_str(), Some(s));
+    }
+}
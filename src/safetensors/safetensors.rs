@@ -0,0 +1,379 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+use ndarray::{Array, ArrayD, ArrayViewD, Dimension, IxDyn};
+
+use super::{
+    json::{quote, Json},
+    safetensors_error::SafetensorsError,
+};
+use crate::model::Model;
+
+/// ヘッダの長さの上限(safetensorsの参照実装と同じ100MB)
+const MAX_HEADER_LEN: u64 = 100_000_000;
+
+/// 書き出し時のテンソルのデータ型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtype {
+    F32,
+    F64,
+}
+
+impl Dtype {
+    fn name(self) -> &'static str {
+        match self {
+            Dtype::F32 => "F32",
+            Dtype::F64 => "F64",
+        }
+    }
+    fn size(self) -> usize {
+        match self {
+            Dtype::F32 => 4,
+            Dtype::F64 => 8,
+        }
+    }
+    fn from_name(name: &str) -> Result<Dtype, SafetensorsError> {
+        match name {
+            "F32" => Ok(Dtype::F32),
+            "F64" => Ok(Dtype::F64),
+            _ => Err(SafetensorsError::UnsupportedDtype(name.to_string())),
+        }
+    }
+}
+
+/// .safetensorsファイルに含まれる全てのテンソルを、f64に変換してデータの並び順に読み込む
+pub fn read_safetensors(
+    path: impl AsRef<Path>,
+) -> Result<Vec<(String, ArrayD<f64>)>, SafetensorsError> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    read_safetensors_from(&bytes)
+}
+
+pub fn read_safetensors_from(bytes: &[u8]) -> Result<Vec<(String, ArrayD<f64>)>, SafetensorsError> {
+    let invalid = |message: &str| SafetensorsError::InvalidHeader(message.to_string());
+    let header_len = bytes
+        .get(..8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("file is too short"))?;
+    if header_len > MAX_HEADER_LEN {
+        return Err(invalid("header is too large"));
+    }
+    let header_end = usize::try_from(header_len)
+        .ok()
+        .and_then(|len| len.checked_add(8))
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| invalid("header length exceeds file size"))?;
+    let header = std::str::from_utf8(&bytes[8..header_end])
+        .map_err(|_| invalid("header is not valid UTF-8"))?;
+    let data = &bytes[header_end..];
+
+    let entries = match Json::parse(header).map_err(SafetensorsError::InvalidHeader)? {
+        Json::Object(entries) => entries,
+        _ => return Err(invalid("header is not a JSON object")),
+    };
+    let mut tensors = Vec::new();
+    for (name, info) in entries {
+        if name == "__metadata__" {
+            continue;
+        }
+        let dtype = Dtype::from_name(
+            info.get("dtype")
+                .and_then(Json::as_str)
+                .ok_or_else(|| invalid(&format!("`{}` has no dtype", name)))?,
+        )?;
+        let shape = info
+            .get("shape")
+            .and_then(Json::as_array)
+            .and_then(|dims| {
+                dims.iter()
+                    .map(|d| d.as_u64().and_then(|d| usize::try_from(d).ok()))
+                    .collect::<Option<Vec<usize>>>()
+            })
+            .ok_or_else(|| invalid(&format!("`{}` has no valid shape", name)))?;
+        let (begin, end) = match info
+            .get("data_offsets")
+            .and_then(Json::as_array)
+            .map(|offsets| offsets.iter().map(Json::as_u64).collect::<Vec<_>>())
+            .as_deref()
+        {
+            Some(&[Some(begin), Some(end)]) => (begin as usize, end as usize),
+            _ => return Err(invalid(&format!("`{}` has no valid data_offsets", name))),
+        };
+        let expected_len = shape
+            .iter()
+            .try_fold(dtype.size(), |len, &d| len.checked_mul(d));
+        if begin > end || end > data.len() || expected_len != Some(end - begin) {
+            return Err(invalid(&format!(
+                "data_offsets of `{}` do not match its shape",
+                name
+            )));
+        }
+        let values = match dtype {
+            Dtype::F32 => data[begin..end]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+                .collect(),
+            Dtype::F64 => data[begin..end]
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+        };
+        tensors.push((begin, name, ArrayD::from_shape_vec(shape, values).unwrap()));
+    }
+    tensors.sort_by_key(|(begin, _, _)| *begin);
+    Ok(tensors
+        .into_iter()
+        .map(|(_, name, tensor)| (name, tensor))
+        .collect())
+}
+
+/// テンソルを、指定したデータ型で.safetensorsファイルとして書き出す
+///
+/// F32を指定した場合は、f64からの変換で精度が落ちる。
+pub fn write_safetensors(
+    path: impl AsRef<Path>,
+    tensors: &[(String, ArrayViewD<f64>)],
+    dtype: Dtype,
+) -> Result<(), SafetensorsError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_safetensors_to(&mut writer, tensors, dtype)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_safetensors_to(
+    writer: &mut impl Write,
+    tensors: &[(String, ArrayViewD<f64>)],
+    dtype: Dtype,
+) -> Result<(), SafetensorsError> {
+    let mut offset = 0;
+    let mut header = tensors
+        .iter()
+        .map(|(name, tensor)| {
+            let end = offset + tensor.len() * dtype.size();
+            let entry = format!(
+                "{}:{{\"dtype\":\"{}\",\"shape\":[{}],\"data_offsets\":[{},{}]}}",
+                quote(name),
+                dtype.name(),
+                tensor
+                    .shape()
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                offset,
+                end
+            );
+            offset = end;
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    header = format!("{{{}}}", header);
+    // データ部の先頭が8バイト境界に揃うよう、空白で詰める
+    while header.len() % 8 != 0 {
+        header.push(' ');
+    }
+
+    writer.write_all(&(header.len() as u64).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for (_, tensor) in tensors {
+        for &value in tensor.iter() {
+            match dtype {
+                Dtype::F32 => writer.write_all(&(value as f32).to_le_bytes())?,
+                Dtype::F64 => writer.write_all(&value.to_le_bytes())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// read_safetensorsで読み込んだテンソルから、名前と次元を指定して取り出す
+pub fn safetensors_entry<D: Dimension>(
+    tensors: &[(String, ArrayD<f64>)],
+    name: &str,
+) -> Result<Array<f64, D>, SafetensorsError> {
+    let tensor = tensors
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, tensor)| tensor)
+        .ok_or_else(|| SafetensorsError::MissingTensor(name.to_string()))?;
+    tensor
+        .clone()
+        .into_dimensionality::<D>()
+        .map_err(|_| SafetensorsError::DimensionMismatch {
+            name: name.to_string(),
+            expected: D::NDIM.unwrap_or(tensor.ndim()),
+            found: tensor.ndim(),
+        })
+}
+
+/// モデルのパラメータとバッファを.safetensorsファイルに書き出す
+///
+/// renameに(モデル内の名前, ファイル内の名前)を渡すと、その名前で書き出す。
+pub fn save_model_safetensors(
+    model: &mut impl Model,
+    path: impl AsRef<Path>,
+    dtype: Dtype,
+    rename: &[(&str, &str)],
+) -> Result<(), SafetensorsError> {
+    let mut tensors = model
+        .parameters()
+        .into_iter()
+        .map(|param| (param.name, param.value.to_owned()))
+        .collect::<Vec<(String, ArrayD<f64>)>>();
+    tensors.extend(
+        model
+            .buffers()
+            .into_iter()
+            .map(|(name, buffer)| (name, buffer.to_owned())),
+    );
+    let views = tensors
+        .iter()
+        .map(|(name, tensor)| (file_name(name, rename).to_string(), tensor.view()))
+        .collect::<Vec<_>>();
+    write_safetensors(path, &views, dtype)
+}
+
+/// .safetensorsファイルから、モデルのパラメータとバッファを読み込む
+///
+/// renameはsave_model_safetensorsと同じ。足りないテンソルや形状の異なるテンソルがあった場合は、
+/// モデルを変更せずにエラーを返す。
+pub fn load_model_safetensors(
+    model: &mut impl Model,
+    path: impl AsRef<Path>,
+    rename: &[(&str, &str)],
+) -> Result<(), SafetensorsError> {
+    let tensors = read_safetensors(path)?;
+    // 全てのテンソルを確認してから書き込み、途中で失敗してもモデルが変更されないようにする
+    let mut shapes = model
+        .parameters()
+        .into_iter()
+        .map(|param| (param.name, param.value.shape().to_vec()))
+        .collect::<Vec<_>>();
+    shapes.extend(
+        model
+            .buffers()
+            .into_iter()
+            .map(|(name, buffer)| (name, buffer.shape().to_vec())),
+    );
+    for (name, shape) in shapes.iter() {
+        let source = safetensors_entry::<IxDyn>(&tensors, file_name(name, rename))?;
+        if source.shape() != shape.as_slice() {
+            return Err(SafetensorsError::ShapeMismatch {
+                name: file_name(name, rename).to_string(),
+                expected: shape.clone(),
+                found: source.shape().to_vec(),
+            });
+        }
+    }
+
+    for mut param in model.parameters() {
+        param.value.assign(&safetensors_entry::<IxDyn>(
+            &tensors,
+            file_name(&param.name, rename),
+        )?);
+    }
+    for (name, mut buffer) in model.buffers() {
+        buffer.assign(&safetensors_entry::<IxDyn>(
+            &tensors,
+            file_name(&name, rename),
+        )?);
+    }
+    Ok(())
+}
+
+fn file_name<'a>(name: &'a str, rename: &[(&str, &'a str)]) -> &'a str {
+    rename
+        .iter()
+        .find(|(model_name, _)| *model_name == name)
+        .map_or(name, |(_, file_name)| file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr2, Ix1, Ix2};
+
+    fn tensors() -> Vec<(String, ArrayD<f64>)> {
+        vec![
+            (
+                "w".to_string(),
+                arr2(&[[0.5, -1.0, 2.0], [3.25, 0.0, 0.1]]).into_dyn(),
+            ),
+            ("b \"1\"".to_string(), arr1(&[1.5, -2.0]).into_dyn()),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let tensors = tensors();
+        let views = tensors
+            .iter()
+            .map(|(name, tensor)| (name.clone(), tensor.view()))
+            .collect::<Vec<_>>();
+        for dtype in [Dtype::F64, Dtype::F32] {
+            let mut bytes = Vec::new();
+            write_safetensors_to(&mut bytes, &views, dtype).unwrap();
+            let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap()) as usize;
+            assert_eq!(header_len % 8, 0);
+            assert_eq!(bytes.len(), 8 + header_len + 8 * dtype.size());
+
+            let read = read_safetensors_from(&bytes).unwrap();
+            assert_eq!(read.len(), 2);
+            let w = safetensors_entry::<Ix2>(&read, "w").unwrap();
+            let expected = arr2(&[[0.5, -1.0, 2.0], [3.25, 0.0, 0.1]]);
+            match dtype {
+                Dtype::F64 => assert_eq!(w, expected),
+                Dtype::F32 => assert_eq!(w, expected.mapv(|x| x as f32 as f64)),
+            }
+            assert_eq!(
+                safetensors_entry::<Ix1>(&read, "b \"1\"").unwrap(),
+                arr1(&[1.5, -2.0])
+            );
+            assert!(matches!(
+                safetensors_entry::<Ix1>(&read, "w"),
+                Err(SafetensorsError::DimensionMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let mut huge = (MAX_HEADER_LEN + 1).to_le_bytes().to_vec();
+        huge.extend_from_slice(b"{}");
+        let header = |json: &str| {
+            let mut bytes = (json.len() as u64).to_le_bytes().to_vec();
+            bytes.extend_from_slice(json.as_bytes());
+            bytes.extend_from_slice(&[0; 8]);
+            bytes
+        };
+        for bytes in [
+            huge,
+            b"\x10\0\0\0\0\0\0\0{}".to_vec(),
+            header("[]"),
+            header(r#"{"w":{"dtype":"F64","shape":[2],"data_offsets":[0,8]}}"#),
+            header(r#"{"w":{"dtype":"F64","shape":[1],"data_offsets":[0,16]}}"#),
+            header(r#"{"w":{"dtype":"F64","shape":[1.],"data_offsets":[0,8]}}"#),
+        ] {
+            assert!(matches!(
+                read_safetensors_from(&bytes),
+                Err(SafetensorsError::InvalidHeader(_))
+            ));
+        }
+        assert!(matches!(
+            read_safetensors_from(&header(
+                r#"{"w":{"dtype":"I8","shape":[8],"data_offsets":[0,8]}}"#
+            )),
+            Err(SafetensorsError::UnsupportedDtype(_))
+        ));
+        let metadata = header(
+            r#"{"__metadata__":{"format":"pt"},"w":{"dtype":"F64","shape":[],"data_offsets":[0,8]}}"#,
+        );
+        assert_eq!(read_safetensors_from(&metadata).unwrap()[0].1.ndim(), 0);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum SafetensorsError {
+    Io(io::Error),
+    InvalidHeader(String),
+    /// F32, F64以外のデータ型
+    UnsupportedDtype(String),
+    MissingTensor(String),
+    /// テンソルの次元数が、読み込み先の次元数と一致しない
+    DimensionMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    ShapeMismatch {
+        name: String,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+}
+
+impl fmt::Display for SafetensorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafetensorsError::Io(e) => write!(f, "I/O error: {}", e),
+            SafetensorsError::InvalidHeader(message) => {
+                write!(f, "invalid safetensors header: {}", message)
+            }
+            SafetensorsError::UnsupportedDtype(dtype) => {
+                write!(f, "unsupported dtype `{}`", dtype)
+            }
+            SafetensorsError::MissingTensor(name) => write!(f, "missing tensor `{}`", name),
+            SafetensorsError::DimensionMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` has {} dimensions, expected {}",
+                name, found, expected
+            ),
+            SafetensorsError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "shape mismatch for `{}`: expected {:?}, found {:?}",
+                name, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SafetensorsError {}
+
+impl From<io::Error> for SafetensorsError {
+    fn from(e: io::Error) -> Self {
+        SafetensorsError::Io(e)
+    }
+}
@@ -0,0 +1,211 @@
+use ndarray::{prelude::*, stack, ArrayViewMutD};
+use ndarray_rand::{rand_distr::Distribution, RandomExt};
+
+use crate::{
+    labels::{accuracy, Labels},
+    layer::{
+        affine_layer::AffineLayer, batch_normalization_layer::BatchNormalizationLayer,
+        layer::Layer, relu_layer::ReluLayer, sequential_layer::SequentialLayer,
+    },
+    model::{Model, Network, Parameter},
+    regularization::Regularization,
+};
+
+/// 層を順に積み重ねたネットワーク。各層は自身のパラメータを所有する。
+#[derive(Default)]
+pub struct Sequential {
+    layers: Vec<Box<dyn SequentialLayer>>,
+    /// 設定した場合、lossに正則化項を加え、gradientに正則化項の勾配を加える
+    pub regularization: Option<Regularization>,
+}
+
+impl Sequential {
+    pub fn new() -> Self {
+        Sequential {
+            layers: Vec::new(),
+            regularization: None,
+        }
+    }
+    /// 各層の大きさ(入力層, 隠れ層..., 出力層)を指定し、
+    /// 隠れ層がAffine→BatchNormalization→ReLUからなる多層パーセプトロンを作る
+    pub fn mlp(sizes: &[usize], dist: &impl Distribution<f64>) -> Self {
+        let mut network = Sequential::new();
+        for (i, size) in sizes.windows(2).enumerate() {
+            let (input_size, output_size) = (size[0], size[1]);
+            network.add(AffineLayer::new_owned(
+                Array2::random((input_size, output_size), dist),
+                Array1::random(output_size, dist),
+            ));
+            if i + 2 < sizes.len() {
+                network.add(BatchNormalizationLayer::new_owned(
+                    stack![
+                        Axis(0),
+                        Array1::ones(output_size),
+                        Array1::zeros(output_size)
+                    ],
+                    Array1::zeros(output_size),
+                    Array1::ones(output_size),
+                    0.9,
+                ));
+                network.add(ReluLayer::<Ix2>::new());
+            }
+        }
+        network
+    }
+    pub fn add(&mut self, layer: impl SequentialLayer + 'static) -> &mut Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+    pub fn predict(&mut self, x: &Array2<f64>, train_flg: bool) -> Array2<f64> {
+        let mut x = x.clone();
+        for layer in self.layers.iter_mut() {
+            layer.set_train_flg(train_flg);
+            x = layer.forward(&x);
+        }
+        x
+    }
+    pub fn loss(&mut self, x: &Array2<f64>, t: &impl Labels) -> f64 {
+        let y = self.predict(x, false);
+        t.loss_layer().forward(&y) + self.penalty()
+    }
+    pub fn accuracy(&mut self, x: &Array2<f64>, t: &impl Labels) -> f64 {
+        let y = self.predict(x, false);
+        accuracy(&y, t)
+    }
+    /// 誤差逆伝播法で勾配を求める。勾配は各層に保持され、parametersやparams_and_gradsで取り出せる
+    pub fn gradient(&mut self, x: &Array2<f64>, t: &impl Labels) {
+        self.gradient_with_loss(x, t.loss_layer().as_mut());
+    }
+    /// 全ての層の(パラメータ, 勾配)の組を、入力側の層から順に返す
+    pub fn params_and_grads(
+        &mut self,
+    ) -> impl Iterator<Item = (ArrayViewMutD<'_, f64>, ArrayViewMutD<'_, f64>)> {
+        self.parameters()
+            .into_iter()
+            .map(|param| (param.value, param.grad))
+    }
+}
+
+impl Network for Sequential {
+    fn predict(&mut self, x: &Array2<f64>, train_flg: bool) -> Array2<f64> {
+        Sequential::predict(self, x, train_flg)
+    }
+    fn gradient_with_loss(
+        &mut self,
+        x: &Array2<f64>,
+        loss: &mut dyn Layer<Array2<f64>, f64>,
+    ) -> (f64, Array2<f64>) {
+        let y = Sequential::predict(self, x, true);
+        let loss_value = loss.forward(&y);
+
+        let mut dout = loss.backward(&1.0);
+        for layer in self.layers.iter_mut().rev() {
+            dout = layer.backward(&dout);
+        }
+        // 正則化はself全体のパラメータを借用するため、一時的に取り出す
+        if let Some(regularization) = self.regularization.take() {
+            regularization.apply(self);
+            self.regularization = Some(regularization);
+        }
+        (loss_value + self.penalty(), y)
+    }
+    fn penalty(&mut self) -> f64 {
+        let Some(regularization) = self.regularization.take() else {
+            return 0.0;
+        };
+        let penalty = regularization.penalty(self);
+        self.regularization = Some(regularization);
+        penalty
+    }
+}
+
+impl Model for Sequential {
+    /// パラメータ名は"層の番号.層内での名前"(例: "0.w")
+    fn parameters(&mut self) -> Vec<Parameter<'_>> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, layer)| {
+                layer.parameters().into_iter().map(move |mut param| {
+                    param.name = format!("{}.{}", i, param.name);
+                    param
+                })
+            })
+            .collect()
+    }
+    fn buffers(&mut self) -> Vec<(String, ArrayViewMutD<'_, f64>)> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, layer)| {
+                layer
+                    .buffers()
+                    .into_iter()
+                    .map(move |(name, buffer)| (format!("{}.{}", i, name), buffer))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subfunction::numerical_gradient::numerical_gradient;
+    use ndarray::{arr1, arr2, ArrayD, ArrayViewD};
+    use std::cell::RefCell;
+
+    fn network() -> Sequential {
+        let mut network = Sequential::new();
+        network
+            .add(AffineLayer::new_owned(
+                arr2(&[[0.2, -0.5, 0.1], [0.4, 0.3, -0.7]]),
+                arr1(&[0.1, 0.0, -0.1]),
+            ))
+            .add(ReluLayer::<Ix2>::new())
+            .add(AffineLayer::new_owned(
+                arr2(&[[0.3, -0.2], [0.6, 0.1], [-0.4, 0.5]]),
+                arr1(&[0.05, -0.05]),
+            ));
+        network
+    }
+
+    #[test]
+    fn parameter_names_are_prefixed_with_layer_index() {
+        let mut network = network();
+        let names = network
+            .parameters()
+            .into_iter()
+            .map(|param| param.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["0.w", "0.b", "2.w", "2.b"]);
+    }
+
+    #[test]
+    fn gradient_matches_numerical_gradient() {
+        let x = arr2(&[[1.0, 2.0], [-1.0, 0.5], [0.3, -0.8]]);
+        let t = Array1::from(vec![0, 1, 1]);
+        let mut network = network();
+        network.gradient(&x, &t);
+        let analytic = network
+            .parameters()
+            .into_iter()
+            .map(|param| param.grad.to_owned())
+            .collect::<Vec<_>>();
+
+        let network = RefCell::new(network);
+        for (i, analytic) in analytic.iter().enumerate() {
+            let value = network.borrow_mut().parameters()[i].value.to_owned();
+            let loss = |w: ArrayViewD<f64>| {
+                let mut network = network.borrow_mut();
+                network.parameters()[i].value.assign(&w);
+                let y = network.predict(&x, true);
+                t.loss_layer().forward(&y)
+            };
+            let numerical: ArrayD<f64> = numerical_gradient(&loss, value.view());
+            network.borrow_mut().parameters()[i].value.assign(&value);
+            for (n, a) in numerical.iter().zip(analytic.iter()) {
+                assert!((n - a).abs() < 1e-6, "numerical: {}, analytic: {}", n, a);
+            }
+        }
+    }
+}
@@ -1,9 +1,13 @@
 pub mod argmax;
+pub mod col2im;
 pub mod cross_entropy_error;
 pub mod identity_function;
+pub mod im2col;
 pub mod numerical_gradient;
+pub mod one_hot;
 pub mod relu;
 pub mod sigmoid;
 pub mod softmax;
 pub mod softmax_batch;
+pub mod sparse_cross_entropy_error;
 pub mod step_function;
@@ -0,0 +1,37 @@
+use ndarray::{s, Array4, Array6, ArrayView2};
+
+use super::im2col::output_size;
+
+/// im2colの逆変換。展開された2次元配列を、4次元の入力(N, C, H, W)の形に戻す
+///
+/// 同じ入力要素に対応する値は足し合わされるため、im2colの逆伝播として用いることができる。
+pub fn col2im(
+    col: ArrayView2<f64>,
+    input_shape: (usize, usize, usize, usize),
+    filter_h: usize,
+    filter_w: usize,
+    stride: usize,
+    pad: usize,
+) -> Array4<f64> {
+    let (n, c, h, w) = input_shape;
+    let out_h = output_size(h, filter_h, stride, pad);
+    let out_w = output_size(w, filter_w, stride, pad);
+
+    let col: Array6<f64> = col
+        .as_standard_layout()
+        .into_owned()
+        .into_shape((n, out_h, out_w, c, filter_h, filter_w))
+        .unwrap()
+        .permuted_axes([0, 3, 4, 5, 1, 2]);
+
+    let mut img = Array4::zeros((n, c, h + 2 * pad, w + 2 * pad));
+    for y in 0..filter_h {
+        let y_max = y + stride * (out_h - 1) + 1;
+        for x in 0..filter_w {
+            let x_max = x + stride * (out_w - 1) + 1;
+            let mut region = img.slice_mut(s![.., .., y..y_max;stride, x..x_max;stride]);
+            region += &col.slice(s![.., .., y, x, .., ..]);
+        }
+    }
+    img.slice(s![.., .., pad..pad + h, pad..pad + w]).to_owned()
+}
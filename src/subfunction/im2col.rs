@@ -0,0 +1,99 @@
+use ndarray::{s, Array2, Array4, Array6, ArrayView4};
+
+/// 4次元の入力(N, C, H, W)を、フィルタを適用する領域ごとに1行へ展開した2次元配列に変換する
+///
+/// 戻り値の形状は(N * out_h * out_w, C * filter_h * filter_w)。
+pub fn im2col(
+    input: ArrayView4<f64>,
+    filter_h: usize,
+    filter_w: usize,
+    stride: usize,
+    pad: usize,
+) -> Array2<f64> {
+    let (n, c, h, w) = input.dim();
+    let out_h = output_size(h, filter_h, stride, pad);
+    let out_w = output_size(w, filter_w, stride, pad);
+
+    let mut img = Array4::zeros((n, c, h + 2 * pad, w + 2 * pad));
+    img.slice_mut(s![.., .., pad..pad + h, pad..pad + w])
+        .assign(&input);
+
+    // col[n, c, y, x, oh, ow] = img[n, c, y + stride * oh, x + stride * ow]
+    let mut col = Array6::zeros((n, c, filter_h, filter_w, out_h, out_w));
+    for y in 0..filter_h {
+        let y_max = y + stride * (out_h - 1) + 1;
+        for x in 0..filter_w {
+            let x_max = x + stride * (out_w - 1) + 1;
+            col.slice_mut(s![.., .., y, x, .., ..])
+                .assign(&img.slice(s![
+                    ..,
+                    ..,
+                    y..y_max;stride,
+                    x..x_max;stride
+                ]));
+        }
+    }
+    col.permuted_axes([0, 4, 5, 1, 2, 3])
+        .as_standard_layout()
+        .into_owned()
+        .into_shape((n * out_h * out_w, c * filter_h * filter_w))
+        .unwrap()
+}
+
+/// 畳み込みやプーリングで、大きさinputの入力にフィルタを適用したときの出力の大きさ
+///
+/// strideが0の場合や、フィルタがパディングを含めた入力より大きい場合はpanicする。
+pub fn output_size(input: usize, filter: usize, stride: usize, pad: usize) -> usize {
+    assert!(stride > 0, "stride must be positive");
+    assert!(
+        filter <= input + 2 * pad,
+        "filter size {} is larger than the padded input size {}",
+        filter,
+        input + 2 * pad
+    );
+    (input + 2 * pad - filter) / stride + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subfunction::col2im::col2im;
+    use ndarray::{arr2, Array};
+
+    #[test]
+    fn rows_are_filter_regions() {
+        let x = Array::range(0.0, 9.0, 1.0)
+            .into_shape((1, 1, 3, 3))
+            .unwrap();
+        let col = im2col(x.view(), 2, 2, 1, 0);
+        assert_eq!(
+            col,
+            arr2(&[
+                [0.0, 1.0, 3.0, 4.0],
+                [1.0, 2.0, 4.0, 5.0],
+                [3.0, 4.0, 6.0, 7.0],
+                [4.0, 5.0, 7.0, 8.0]
+            ])
+        );
+    }
+
+    #[test]
+    fn col2im_is_adjoint_of_im2col() {
+        // <im2col(x), c> == <x, col2im(c)> なら、col2imはim2colの逆伝播になっている
+        let shape = (2, 3, 5, 4);
+        let x = Array::range(0.0, 120.0, 1.0).into_shape(shape).unwrap();
+        let col = im2col(x.view(), 3, 2, 2, 1);
+        let c = Array::range(0.0, col.len() as f64, 1.0)
+            .into_shape(col.raw_dim())
+            .unwrap();
+        let lhs = (&col * &c).sum();
+        let rhs = (&x * &col2im(c.view(), shape, 3, 2, 2, 1)).sum();
+        assert_eq!(lhs, rhs);
+    }
+
+    #[test]
+    fn output_size_with_padding_and_stride() {
+        assert_eq!(output_size(28, 5, 1, 0), 24);
+        assert_eq!(output_size(5, 3, 2, 1), 3);
+    }
+}
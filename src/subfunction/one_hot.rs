@@ -0,0 +1,9 @@
+use ndarray::{Array1, Array2};
+
+/// クラスのインデックスをone-hot形式に変換する
+pub fn one_hot(t: &Array1<usize>, classes: usize) -> Array2<f64> {
+    Array2::from_shape_fn(
+        (t.len(), classes),
+        |(i, j)| if t[i] == j { 1.0 } else { 0.0 },
+    )
+}
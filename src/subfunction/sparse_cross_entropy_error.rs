@@ -0,0 +1,16 @@
+use ndarray::{ArrayView1, ArrayView2, Axis};
+
+/// 正解ラベルをクラスのインデックスで受け取る交差エントロピー誤差
+///
+/// one-hot形式のtを用いたcross_entropy_errorと同じ値になる。
+/// yの行数とtの長さが異なる場合はpanicする。
+pub fn sparse_cross_entropy_error(y: ArrayView2<f64>, t: ArrayView1<usize>) -> f64 {
+    let delta = 1e-7;
+    let batch_size = y.raw_dim()[0];
+    assert_eq!(batch_size, t.len(), "batch size of y and t must match");
+    -y.axis_iter(Axis(0))
+        .zip(t.iter())
+        .map(|(y, &t)| (y[t] + delta).ln())
+        .sum::<f64>()
+        / batch_size as f64
+}
@@ -0,0 +1,4 @@
+pub mod early_stopping;
+pub mod history;
+#[allow(clippy::module_inception)]
+pub mod trainer;
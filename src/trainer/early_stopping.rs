@@ -0,0 +1,102 @@
+use ndarray::ArrayD;
+
+use super::history::Evaluation;
+use crate::model::Model;
+
+/// 早期終了で監視する値
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Monitor {
+    /// 検証データでの損失。小さいほど良い
+    ValidationLoss,
+    /// 検証データでの認識精度。大きいほど良い
+    ValidationAccuracy,
+}
+
+/// 検証データでの評価がpatience回続けて改善しなかった場合に学習を打ち切る
+///
+/// 最も良かった時点のパラメータとバッファを保持し、restoreでモデルへ書き戻す。
+pub struct EarlyStopping {
+    monitor: Monitor,
+    patience: usize,
+    /// これより小さい変化は改善とみなさない
+    min_delta: f64,
+    restore_best_weights: bool,
+    best: Option<Evaluation>,
+    best_weights: Vec<ArrayD<f64>>,
+    wait: usize,
+}
+
+impl EarlyStopping {
+    pub fn new(monitor: Monitor, patience: usize, min_delta: f64) -> Self {
+        EarlyStopping {
+            monitor,
+            patience,
+            min_delta,
+            restore_best_weights: true,
+            best: None,
+            best_weights: Vec::new(),
+            wait: 0,
+        }
+    }
+    /// falseの場合、最良のパラメータを保持せず、restoreでも何もしない
+    pub fn with_restore_best_weights(mut self, restore_best_weights: bool) -> Self {
+        self.restore_best_weights = restore_best_weights;
+        self
+    }
+    /// これまでで最も良かった評価結果
+    pub fn best(&self) -> Option<&Evaluation> {
+        self.best.as_ref()
+    }
+    /// 評価結果を記録し、学習を打ち切るべき場合はtrueを返す
+    pub fn observe(&mut self, evaluation: &Evaluation, model: &mut impl Model) -> bool {
+        if self.is_improvement(evaluation) {
+            self.best = Some(*evaluation);
+            self.wait = 0;
+            if self.restore_best_weights {
+                self.best_weights = snapshot(model);
+            }
+            false
+        } else {
+            self.wait += 1;
+            self.wait >= self.patience
+        }
+    }
+    /// 最も良かった時点のパラメータとバッファをモデルへ書き戻す
+    pub fn restore(&self, model: &mut impl Model) {
+        if self.best_weights.is_empty() {
+            return;
+        }
+        // snapshotと同じく、パラメータ、バッファの順に並んでいる
+        let (best_params, best_buffers) = self.best_weights.split_at(model.parameters().len());
+        for (mut param, best) in model.parameters().into_iter().zip(best_params) {
+            param.value.assign(best);
+        }
+        for ((_, mut buffer), best) in model.buffers().into_iter().zip(best_buffers) {
+            buffer.assign(best);
+        }
+    }
+    fn is_improvement(&self, evaluation: &Evaluation) -> bool {
+        match (&self.best, self.monitor) {
+            (None, _) => true,
+            (Some(best), Monitor::ValidationLoss) => evaluation.loss < best.loss - self.min_delta,
+            (Some(best), Monitor::ValidationAccuracy) => {
+                evaluation.accuracy > best.accuracy + self.min_delta
+            }
+        }
+    }
+}
+
+fn snapshot(model: &mut impl Model) -> Vec<ArrayD<f64>> {
+    let mut weights = model
+        .parameters()
+        .into_iter()
+        .map(|param| param.value.to_owned())
+        .collect::<Vec<_>>();
+    weights.extend(
+        model
+            .buffers()
+            .into_iter()
+            .map(|(_, buffer)| buffer.to_owned()),
+    );
+    weights
+}
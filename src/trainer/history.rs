@@ -0,0 +1,59 @@
+/// 検証データでの評価結果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Evaluation {
+    /// 評価した時点で終えたエポック数
+    pub epoch: usize,
+    /// 評価した時点で終えたイテレーション数
+    pub iteration: usize,
+    pub loss: f64,
+    pub accuracy: f64,
+}
+
+/// Trainerの学習の記録
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    /// 各ミニバッチの損失
+    pub batch_loss: Vec<f64>,
+    /// 各ミニバッチの、学習時の出力での認識精度
+    pub batch_accuracy: Vec<f64>,
+    /// 各エポックのミニバッチの損失の平均
+    pub epoch_loss: Vec<f64>,
+    /// 各エポックのミニバッチの認識精度の平均
+    pub epoch_accuracy: Vec<f64>,
+    /// 検証データでの評価結果を、評価した順に並べたもの
+    pub validation: Vec<Evaluation>,
+}
+
+impl History {
+    /// 検証データでの損失が最小の評価結果
+    pub fn best_validation(&self) -> Option<&Evaluation> {
+        self.validation
+            .iter()
+            .min_by(|a, b| a.loss.total_cmp(&b.loss))
+    }
+}
+
+/// 各ミニバッチの学習を終えたときにコールバックへ渡す情報
+#[derive(Clone, Copy, Debug)]
+pub struct BatchEnd {
+    /// 学習中のエポック(0始まり)
+    pub epoch: usize,
+    /// このミニバッチを含めて終えたイテレーション数
+    pub iteration: usize,
+    pub loss: f64,
+    pub accuracy: f64,
+}
+
+/// 各エポックの学習を終えたときにコールバックへ渡す情報
+#[derive(Clone, Copy, Debug)]
+pub struct EpochEnd {
+    /// 終えたエポック(0始まり)
+    pub epoch: usize,
+    pub iteration: usize,
+    /// ミニバッチの損失の平均
+    pub loss: f64,
+    /// ミニバッチの認識精度の平均
+    pub accuracy: f64,
+    /// このエポックの最後に検証した場合、その結果
+    pub validation: Option<Evaluation>,
+}
@@ -0,0 +1,434 @@
+use ndarray::{Array2, IxDyn};
+
+use super::{
+    early_stopping::EarlyStopping,
+    history::{BatchEnd, EpochEnd, Evaluation, History},
+};
+use crate::{
+    data_loader::DataLoader,
+    labels::{accuracy, Labels},
+    layer::layer::Layer,
+    model::Network,
+    optimize::{optimize::Optimize, optimizer::Optimizer},
+};
+
+/// 損失関数の層を、正解ラベルから作る関数
+pub type LossFn<L> = Box<dyn Fn(&L) -> Box<dyn Layer<Array2<f64>, f64>>>;
+type Callback<T> = Box<dyn FnMut(&T)>;
+
+/// 検証データで評価するタイミング
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationSchedule {
+    /// nエポックごとに、エポックの最後で評価する
+    EveryEpochs(usize),
+    /// nイテレーションごとに評価する
+    EveryIterations(usize),
+}
+
+/// モデルをDataLoaderのミニバッチで学習させ、検証と記録を行う
+///
+/// # Examples
+/// ```
+/// # use ndarray::{arr1, arr2};
+/// # use ndarray_rand::rand_distr::Normal;
+/// # use zero_deeplearning::{
+/// #     data_loader::DataLoader,
+/// #     labels::Labels,
+/// #     optimize::{optimizer::Optimizer, sgd::SGD},
+/// #     sequential::Sequential,
+/// #     trainer::trainer::{Trainer, ValidationSchedule},
+/// # };
+/// # let (x, t) = (arr2(&[[0.0, 1.0], [1.0, 0.0]]), arr1(&[1, 0]));
+/// # let (x_val, t_val) = (x.clone(), t.clone());
+/// # let network = Sequential::mlp(&[2, 4, 2], &Normal::new(0.0, 0.1).unwrap());
+/// # let optimizer = Optimizer::new(|| SGD::new(0.1));
+/// # let loader = DataLoader::new(x, t, 2);
+/// let mut trainer = Trainer::new(network, optimizer, Labels::loss_layer, loader)
+///     .with_validation(x_val, t_val, ValidationSchedule::EveryEpochs(1));
+/// trainer.on_epoch_end(|e| println!("epoch {}: {}", e.epoch, e.loss));
+/// trainer.fit(20);
+/// ```
+pub struct Trainer<M: Network, O: Optimize<IxDyn>, L: Labels> {
+    model: M,
+    optimizer: Optimizer<O>,
+    loss: LossFn<L>,
+    loader: DataLoader<L>,
+    validation: Option<(Array2<f64>, L, ValidationSchedule)>,
+    history: History,
+    early_stopping: Option<EarlyStopping>,
+    /// 早期終了で学習を打ち切ったか
+    stopped: bool,
+    epoch: usize,
+    iteration: usize,
+    batch_callbacks: Vec<Callback<BatchEnd>>,
+    epoch_callbacks: Vec<Callback<EpochEnd>>,
+}
+
+impl<M: Network, O: Optimize<IxDyn>, L: Labels + Send + Sync + 'static> Trainer<M, O, L> {
+    /// lossには、正解ラベルから損失関数の層を作る関数を渡す。通常はLabels::loss_layerでよい
+    pub fn new(
+        model: M,
+        optimizer: Optimizer<O>,
+        loss: impl Fn(&L) -> Box<dyn Layer<Array2<f64>, f64>> + 'static,
+        loader: DataLoader<L>,
+    ) -> Self {
+        Trainer {
+            model,
+            optimizer,
+            loss: Box::new(loss),
+            loader,
+            validation: None,
+            history: History::default(),
+            early_stopping: None,
+            stopped: false,
+            epoch: 0,
+            iteration: 0,
+            batch_callbacks: Vec::new(),
+            epoch_callbacks: Vec::new(),
+        }
+    }
+    /// scheduleのタイミングで検証データ(x, t)を評価し、Historyに記録する
+    ///
+    /// 学習率のスケジューラがある場合は、検証データでの損失をreport_metricで渡す。
+    pub fn with_validation(mut self, x: Array2<f64>, t: L, schedule: ValidationSchedule) -> Self {
+        self.validation = Some((x, t, schedule));
+        self
+    }
+    /// 検証のたびにearly_stoppingで評価結果を監視し、改善しなくなったら学習を打ち切る
+    ///
+    /// with_validationと合わせて使う。打ち切った時点で最良のパラメータへ戻す。
+    /// 最後のエポックまで学習した場合は、続けてfitできるよう最後のパラメータのままにするため、
+    /// 学習を終えるときにrestore_best_weightsを呼ぶ。
+    pub fn with_early_stopping(mut self, early_stopping: EarlyStopping) -> Self {
+        self.early_stopping = Some(early_stopping);
+        self
+    }
+    /// 各ミニバッチの学習を終えるたびに呼ばれる関数を登録する
+    pub fn on_batch_end(&mut self, callback: impl FnMut(&BatchEnd) + 'static) -> &mut Self {
+        self.batch_callbacks.push(Box::new(callback));
+        self
+    }
+    /// 各エポックの学習を終えるたびに呼ばれる関数を登録する
+    pub fn on_epoch_end(&mut self, callback: impl FnMut(&EpochEnd) + 'static) -> &mut Self {
+        self.epoch_callbacks.push(Box::new(callback));
+        self
+    }
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+    pub fn model_mut(&mut self) -> &mut M {
+        &mut self.model
+    }
+    pub fn optimizer(&self) -> &Optimizer<O> {
+        &self.optimizer
+    }
+    pub fn optimizer_mut(&mut self) -> &mut Optimizer<O> {
+        &mut self.optimizer
+    }
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+    pub fn early_stopping(&self) -> Option<&EarlyStopping> {
+        self.early_stopping.as_ref()
+    }
+    /// 早期終了で学習を打ち切った場合はtrue
+    pub fn stopped_early(&self) -> bool {
+        self.stopped
+    }
+    /// 早期終了で監視した中で最も良かったパラメータへ戻す。早期終了を設定していない場合は何もしない
+    pub fn restore_best_weights(&mut self) {
+        if let Some(early_stopping) = self.early_stopping.as_ref() {
+            early_stopping.restore(&mut self.model);
+        }
+    }
+    /// 学習したモデルを取り出す
+    pub fn into_model(self) -> M {
+        self.model
+    }
+    /// epochsエポック学習する。続けて呼んだ場合は、前回の続きから学習する
+    ///
+    /// 早期終了した場合はそのエポックの途中で終わって最良のパラメータへ戻り、以降のfitでは何もしない。
+    pub fn fit(&mut self, epochs: usize) -> &History {
+        for _ in 0..epochs {
+            if self.stopped {
+                break;
+            }
+            let (mut loss_sum, mut accuracy_sum) = (0.0, 0.0);
+            let mut batches = 0;
+            let mut validation = None;
+            for (x_batch, t_batch) in self.loader.epoch() {
+                let (loss, y) = self
+                    .model
+                    .gradient_with_loss(&x_batch, (self.loss)(&t_batch).as_mut());
+                let accuracy = accuracy(&y, &t_batch);
+                self.optimizer.step(&mut self.model);
+                self.iteration += 1;
+                loss_sum += loss;
+                accuracy_sum += accuracy;
+                batches += 1;
+                self.history.batch_loss.push(loss);
+                self.history.batch_accuracy.push(accuracy);
+                let batch_end = BatchEnd {
+                    epoch: self.epoch,
+                    iteration: self.iteration,
+                    loss,
+                    accuracy,
+                };
+                for callback in self.batch_callbacks.iter_mut() {
+                    callback(&batch_end);
+                }
+                if let Some((_, _, ValidationSchedule::EveryIterations(n))) = self.validation {
+                    if self.iteration.is_multiple_of(n) {
+                        validation = self.validate();
+                    }
+                }
+                if self.stopped {
+                    break;
+                }
+            }
+            self.epoch += 1;
+            if let Some((_, _, ValidationSchedule::EveryEpochs(n))) = self.validation {
+                if self.epoch.is_multiple_of(n) {
+                    validation = self.validate();
+                }
+            }
+            let loss = loss_sum / batches.max(1) as f64;
+            let accuracy = accuracy_sum / batches.max(1) as f64;
+            self.history.epoch_loss.push(loss);
+            self.history.epoch_accuracy.push(accuracy);
+            // 最後のミニバッチの後に検証した場合のみ、エポックの検証結果とする
+            let epoch_end = EpochEnd {
+                epoch: self.epoch - 1,
+                iteration: self.iteration,
+                loss,
+                accuracy,
+                validation: validation.filter(|v| v.iteration == self.iteration),
+            };
+            for callback in self.epoch_callbacks.iter_mut() {
+                callback(&epoch_end);
+            }
+        }
+        &self.history
+    }
+    /// (x, t)での損失と認識精度を求める。Historyには記録しない
+    pub fn evaluate(&mut self, x: &Array2<f64>, t: &L) -> Evaluation {
+        let y = self.model.predict(x, false);
+        Evaluation {
+            epoch: self.epoch,
+            iteration: self.iteration,
+            loss: (self.loss)(t).forward(&y) + self.model.penalty(),
+            accuracy: accuracy(&y, t),
+        }
+    }
+    fn validate(&mut self) -> Option<Evaluation> {
+        let (x, t, schedule) = self.validation.take()?;
+        let evaluation = self.evaluate(&x, &t);
+        self.validation = Some((x, t, schedule));
+        if let Some(scheduler) = self.optimizer.scheduler_mut() {
+            scheduler.report_metric(evaluation.loss);
+        }
+        self.history.validation.push(evaluation);
+        if let Some(early_stopping) = self.early_stopping.as_mut() {
+            if early_stopping.observe(&evaluation, &mut self.model) {
+                self.stopped = true;
+                early_stopping.restore(&mut self.model);
+            }
+        }
+        Some(evaluation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layer::affine_layer::AffineLayer, model::Model, optimize::sgd::SGD, sequential::Sequential,
+        trainer::early_stopping::Monitor,
+    };
+    use ndarray::{arr1, arr2, Array1, ArrayD};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// x[0]の符号でクラスが決まる8個のデータ
+    fn data() -> (Array2<f64>, Array1<usize>) {
+        let x = arr2(&[
+            [1.0, 0.5],
+            [-1.0, 0.2],
+            [0.8, -0.3],
+            [-0.6, -0.9],
+            [0.3, 1.0],
+            [-0.2, 0.4],
+            [1.5, -1.2],
+            [-1.1, 0.0],
+        ]);
+        let t = x.column(0).mapv(|v| usize::from(v > 0.0));
+        (x, t)
+    }
+
+    fn new_trainer(batch_size: usize) -> Trainer<Sequential, SGD<IxDyn>, Array1<usize>> {
+        let mut network = Sequential::new();
+        network.add(AffineLayer::new_owned(
+            arr2(&[[0.1, -0.1], [0.2, 0.05]]),
+            arr1(&[0.0, 0.0]),
+        ));
+        let (x, t) = data();
+        let loader = DataLoader::new(x, t, batch_size).with_shuffle(false);
+        Trainer::new(
+            network,
+            Optimizer::new(|| SGD::new(0.5)),
+            Labels::loss_layer,
+            loader,
+        )
+    }
+
+    #[test]
+    fn records_history_and_calls_callbacks() {
+        let (x, t) = data();
+        let mut trainer =
+            new_trainer(3).with_validation(x, t, ValidationSchedule::EveryIterations(2));
+        let batch_ends = Rc::new(RefCell::new(Vec::new()));
+        let epoch_ends = Rc::new(RefCell::new(Vec::new()));
+        let (b, e) = (batch_ends.clone(), epoch_ends.clone());
+        trainer
+            .on_batch_end(move |batch| b.borrow_mut().push((batch.epoch, batch.iteration)))
+            .on_epoch_end(move |epoch| e.borrow_mut().push(*epoch));
+        trainer.fit(2);
+
+        // 8個のデータをbatch_size 3で分けると、1エポックは3つのミニバッチになる
+        let history = trainer.history();
+        assert_eq!(history.batch_loss.len(), 6);
+        assert_eq!(history.epoch_loss.len(), 2);
+        assert!(
+            (history.epoch_loss[0] - history.batch_loss[..3].iter().sum::<f64>() / 3.0).abs()
+                < 1e-12
+        );
+        assert_eq!(
+            history
+                .validation
+                .iter()
+                .map(|v| v.iteration)
+                .collect::<Vec<_>>(),
+            vec![2, 4, 6]
+        );
+        assert_eq!(
+            *batch_ends.borrow(),
+            vec![(0, 1), (0, 2), (0, 3), (1, 4), (1, 5), (1, 6)]
+        );
+        let epoch_ends = epoch_ends.borrow();
+        assert_eq!(
+            epoch_ends.iter().map(|e| e.epoch).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        // 1エポック目の最後(3イテレーション目)には検証していない
+        assert!(epoch_ends[0].validation.is_none());
+        assert_eq!(epoch_ends[1].validation, Some(history.validation[2]));
+    }
+
+    #[test]
+    fn continues_training_across_fit_calls() {
+        let (x, t) = data();
+        let mut once = new_trainer(4);
+        once.fit(5);
+        let mut twice = new_trainer(4).with_validation(
+            x.clone(),
+            t.clone(),
+            ValidationSchedule::EveryEpochs(2),
+        );
+        twice.fit(2);
+        twice.fit(3);
+
+        assert_eq!(twice.history().epoch_loss, once.history().epoch_loss);
+        assert_eq!(
+            twice
+                .history()
+                .validation
+                .iter()
+                .map(|v| (v.epoch, v.iteration))
+                .collect::<Vec<_>>(),
+            vec![(2, 4), (4, 8)]
+        );
+        for (a, b) in once
+            .model_mut()
+            .parameters()
+            .iter()
+            .zip(twice.model_mut().parameters().iter())
+        {
+            assert_eq!(a.value, b.value);
+        }
+        // 学習で損失が下がり、全て正しく分類できるようになる
+        let losses = &once.history().epoch_loss;
+        assert!(losses[4] < losses[0]);
+        assert_eq!(once.evaluate(&x, &t).accuracy, 1.0);
+    }
+
+    fn parameters(
+        trainer: &mut Trainer<Sequential, SGD<IxDyn>, Array1<usize>>,
+    ) -> Vec<ArrayD<f64>> {
+        trainer
+            .model_mut()
+            .parameters()
+            .into_iter()
+            .map(|p| p.value.to_owned())
+            .collect()
+    }
+
+    /// 検証データのラベルを訓練データと逆にし、学習するほど検証データでの損失が大きくなるようにする
+    fn diverging_trainer(
+        early_stopping: EarlyStopping,
+    ) -> Trainer<Sequential, SGD<IxDyn>, Array1<usize>> {
+        let (x, t) = data();
+        new_trainer(4)
+            .with_validation(x, t.mapv(|t| 1 - t), ValidationSchedule::EveryEpochs(1))
+            .with_early_stopping(early_stopping)
+    }
+
+    #[test]
+    fn restores_best_weights_when_patience_runs_out() {
+        let mut after_first_epoch = new_trainer(4);
+        after_first_epoch.fit(1);
+
+        let mut trainer = diverging_trainer(EarlyStopping::new(Monitor::ValidationLoss, 2, 0.0));
+        trainer.fit(10);
+        assert!(trainer.stopped_early());
+        assert_eq!(trainer.history().epoch_loss.len(), 3);
+        assert_eq!(trainer.early_stopping().unwrap().best().unwrap().epoch, 1);
+        assert_eq!(parameters(&mut trainer), parameters(&mut after_first_epoch));
+    }
+
+    #[test]
+    fn keeps_latest_weights_until_restored_when_training_runs_to_the_end() {
+        let mut after_first_epoch = new_trainer(4);
+        after_first_epoch.fit(1);
+        let mut after_last_epoch = new_trainer(4);
+        after_last_epoch.fit(4);
+
+        let mut trainer = diverging_trainer(EarlyStopping::new(Monitor::ValidationLoss, 10, 0.0));
+        trainer.fit(4);
+        assert!(!trainer.stopped_early());
+        assert_eq!(trainer.history().validation.len(), 4);
+        assert_eq!(parameters(&mut trainer), parameters(&mut after_last_epoch));
+        trainer.restore_best_weights();
+        assert_eq!(parameters(&mut trainer), parameters(&mut after_first_epoch));
+
+        let mut trainer = diverging_trainer(
+            EarlyStopping::new(Monitor::ValidationLoss, 10, 0.0).with_restore_best_weights(false),
+        );
+        trainer.fit(4);
+        trainer.restore_best_weights();
+        assert_eq!(parameters(&mut trainer), parameters(&mut after_last_epoch));
+    }
+
+    #[test]
+    fn second_fit_resumes_from_the_latest_weights() {
+        let mut continuous = new_trainer(4);
+        continuous.fit(4);
+
+        let mut trainer = diverging_trainer(EarlyStopping::new(Monitor::ValidationLoss, 10, 0.0));
+        trainer.fit(2);
+        trainer.fit(2);
+        assert_eq!(
+            trainer.history().epoch_loss,
+            continuous.history().epoch_loss
+        );
+        assert_eq!(parameters(&mut trainer), parameters(&mut continuous));
+    }
+}
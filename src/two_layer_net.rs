@@ -1,27 +1,46 @@
-use ndarray::prelude::*;
+use std::path::Path;
+
+use ndarray::{prelude::*, stack, ArrayViewD, ArrayViewMutD};
 use ndarray_rand::{rand_distr::Distribution, RandomExt};
 
 use crate::{
+    labels::{accuracy, Labels},
     layer::{
         affine_layer::AffineLayer, batch_normalization_layer::BatchNormalizationLayer,
-        layer::Layer, relu_layer::ReluLayer, softmax_with_loss_layer::SoftmaxWithLossLayer,
+        layer::Layer, relu_layer::ReluLayer,
+    },
+    model::{Model, Network, Parameter},
+    numpy::{
+        npy_error::NpyError,
+        npz::{read_npz, write_npz},
+    },
+    regularization::Regularization,
+    safetensors::{
+        safetensors::{read_safetensors, write_safetensors, Dtype},
+        safetensors_error::SafetensorsError,
     },
-    subfunction::argmax::argmax,
 };
 
 #[derive(Clone)]
 pub struct TwoLayerNet {
     pub w1: Array2<f64>,
     pub b1: Array1<f64>,
-    pub batch_aff: Array1<f64>,
+    pub batch_aff: Array2<f64>,
+    pub batch_running_mean: Array1<f64>,
+    pub batch_running_var: Array1<f64>,
     pub w2: Array2<f64>,
     pub b2: Array1<f64>,
+    /// 直前のgradientの呼び出しで求めた勾配
+    pub grad: TwoLayerNetGradient,
+    /// 設定した場合、lossに正則化項を加え、gradientに正則化項の勾配を加える
+    pub regularization: Option<Regularization>,
 }
 
+#[derive(Clone)]
 pub struct TwoLayerNetGradient {
     pub dw1: Array2<f64>,
     pub db1: Array1<f64>,
-    pub dbatch_aff: Array1<f64>,
+    pub dbatch_aff: Array2<f64>,
     pub dw2: Array2<f64>,
     pub db2: Array1<f64>,
 }
@@ -37,57 +56,270 @@ impl TwoLayerNet {
         let b1 = Array1::random(hidden_size, &dist);
         let w2 = Array2::random((hidden_size, output_size), &dist);
         let b2 = Array1::random(output_size, &dist);
+        let grad = TwoLayerNetGradient {
+            dw1: Array2::zeros(w1.raw_dim()),
+            db1: Array1::zeros(b1.raw_dim()),
+            dbatch_aff: Array2::zeros((2, hidden_size)),
+            dw2: Array2::zeros(w2.raw_dim()),
+            db2: Array1::zeros(b2.raw_dim()),
+        };
         TwoLayerNet {
             w1,
             b1,
-            batch_aff: array![1.0, 0.0],
+            batch_aff: stack![
+                Axis(0),
+                Array1::ones(hidden_size),
+                Array1::zeros(hidden_size)
+            ],
+            batch_running_mean: Array1::zeros(hidden_size),
+            batch_running_var: Array1::ones(hidden_size),
             w2,
             b2,
+            grad,
+            regularization: None,
         }
     }
-    pub fn create_affine1(&self) -> AffineLayer {
+    pub fn create_affine1(&self) -> AffineLayer<'_> {
         AffineLayer::new(&self.w1, &self.b1)
     }
-    pub fn create_batch_normalization1(&self) -> BatchNormalizationLayer {
-        BatchNormalizationLayer::new(self.w1.shape()[1], &self.batch_aff)
+    pub fn create_batch_normalization1(&self) -> BatchNormalizationLayer<'_> {
+        BatchNormalizationLayer::new(
+            &self.batch_aff,
+            self.batch_running_mean.clone(),
+            self.batch_running_var.clone(),
+            0.9,
+        )
     }
     pub fn create_relu1(&self) -> ReluLayer<Ix2> {
         ReluLayer::new()
     }
-    pub fn create_affine2(&self) -> AffineLayer {
+    pub fn create_affine2(&self) -> AffineLayer<'_> {
         AffineLayer::new(&self.w2, &self.b2)
     }
-    pub fn predict(&mut self, x: &Array2<f64>) -> Array2<f64> {
+    /// `train_flg`がtrueの場合、バッチ正規化はミニバッチの統計量を用い、移動平均を更新する。
+    /// falseの場合は移動平均を用いるため、出力はミニバッチの構成に依存しない。
+    pub fn predict(&mut self, x: &Array2<f64>, train_flg: bool) -> Array2<f64> {
         let mut affine1 = self.create_affine1();
         let mut batch_normalization1 = self.create_batch_normalization1();
+        batch_normalization1.set_train_flg(train_flg);
         let mut relu1 = self.create_relu1();
         let mut affine2 = self.create_affine2();
         let mut x = affine1.forward(x);
         x = batch_normalization1.forward(&x);
         x = relu1.forward(&x);
         x = affine2.forward(&x);
+        (self.batch_running_mean, self.batch_running_var) = (
+            batch_normalization1.running_mean,
+            batch_normalization1.running_var,
+        );
         x
     }
-    pub fn loss(&mut self, x: &Array2<f64>, t: &Array2<f64>) -> f64 {
-        let y = self.predict(x);
-        let mut last_layer = SoftmaxWithLossLayer::new(t);
-        last_layer.forward(&y)
-    }
-    pub fn accuracy(&mut self, x: &Array2<f64>, t: &Array2<f64>) -> f64 {
-        let y = self.predict(x);
-        let mut count = 0;
-        for row in 0..y.shape()[0] {
-            let y = y.index_axis(Axis(0), row);
-            let t = t.index_axis(Axis(0), row);
-            let y = argmax(y.view());
-            let t = argmax(t.view());
-            if y == t {
-                count += 1;
-            }
+    pub fn loss(&mut self, x: &Array2<f64>, t: &impl Labels) -> f64 {
+        let y = self.predict(x, false);
+        t.loss_layer().forward(&y) + self.penalty()
+    }
+    pub fn accuracy(&mut self, x: &Array2<f64>, t: &impl Labels) -> f64 {
+        let y = self.predict(x, false);
+        accuracy(&y, t)
+    }
+    pub fn gradient(&mut self, x: &Array2<f64>, t: &impl Labels) -> TwoLayerNetGradient {
+        self.gradient_with_loss(x, t.loss_layer().as_mut());
+        self.grad.clone()
+    }
+    /// 書籍のPython実装(MultiLayerNetExtend)と同じ名前(W1, b1, gamma1, beta1, W2, b2)の配列を
+    /// 含む.npzファイルからパラメータを読み込む。running_mean1, running_var1があれば移動平均も読み込む
+    pub fn load_npz(&mut self, path: impl AsRef<Path>) -> Result<(), NpyError> {
+        let arrays = read_npz(path)?;
+        self.load_named_arrays(&arrays).map_err(|e| match e {
+            NamedArrayError::Missing(name) => NpyError::MissingArray(name),
+            NamedArrayError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => NpyError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            },
+        })
+    }
+    /// load_npzと同じ名前で、パラメータと移動平均を.npzファイルに書き出す
+    pub fn save_npz(&self, path: impl AsRef<Path>) -> Result<(), NpyError> {
+        write_npz(path, &self.named_arrays())
+    }
+    /// load_npzと同じ名前のテンソルを含む.safetensorsファイルからパラメータを読み込む
+    pub fn load_safetensors(&mut self, path: impl AsRef<Path>) -> Result<(), SafetensorsError> {
+        let tensors = read_safetensors(path)?;
+        self.load_named_arrays(&tensors).map_err(|e| match e {
+            NamedArrayError::Missing(name) => SafetensorsError::MissingTensor(name),
+            NamedArrayError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => SafetensorsError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            },
+        })
+    }
+    /// load_npzと同じ名前で、パラメータと移動平均を.safetensorsファイルに書き出す
+    pub fn save_safetensors(
+        &self,
+        path: impl AsRef<Path>,
+        dtype: Dtype,
+    ) -> Result<(), SafetensorsError> {
+        write_safetensors(path, &self.named_arrays(), dtype)
+    }
+    fn named_arrays(&self) -> Vec<(String, ArrayViewD<'_, f64>)> {
+        vec![
+            ("W1".to_string(), self.w1.view().into_dyn()),
+            ("b1".to_string(), self.b1.view().into_dyn()),
+            (
+                "gamma1".to_string(),
+                self.batch_aff.index_axis(Axis(0), 0).into_dyn(),
+            ),
+            (
+                "beta1".to_string(),
+                self.batch_aff.index_axis(Axis(0), 1).into_dyn(),
+            ),
+            ("W2".to_string(), self.w2.view().into_dyn()),
+            ("b2".to_string(), self.b2.view().into_dyn()),
+            (
+                "running_mean1".to_string(),
+                self.batch_running_mean.view().into_dyn(),
+            ),
+            (
+                "running_var1".to_string(),
+                self.batch_running_var.view().into_dyn(),
+            ),
+        ]
+    }
+    /// named_arraysと同じ名前の配列を読み込む。全ての形状を確認してから書き込むので、
+    /// エラーの場合は何も変更しない
+    fn load_named_arrays(
+        &mut self,
+        arrays: &[(String, ArrayD<f64>)],
+    ) -> Result<(), NamedArrayError> {
+        let hidden = self.batch_running_mean.len();
+        let w1 = required_array::<Ix2>(arrays, "W1", self.w1.shape())?;
+        let b1 = required_array::<Ix1>(arrays, "b1", self.b1.shape())?;
+        let gamma1 = required_array::<Ix1>(arrays, "gamma1", &[hidden])?;
+        let beta1 = required_array::<Ix1>(arrays, "beta1", &[hidden])?;
+        let w2 = required_array::<Ix2>(arrays, "W2", self.w2.shape())?;
+        let b2 = required_array::<Ix1>(arrays, "b2", self.b2.shape())?;
+        let running_mean1 = named_array::<Ix1>(arrays, "running_mean1", &[hidden])?;
+        let running_var1 = named_array::<Ix1>(arrays, "running_var1", &[hidden])?;
+
+        self.w1 = w1;
+        self.b1 = b1;
+        self.batch_aff = stack![Axis(0), gamma1, beta1];
+        self.w2 = w2;
+        self.b2 = b2;
+        if let Some(running_mean1) = running_mean1 {
+            self.batch_running_mean = running_mean1;
+        }
+        if let Some(running_var1) = running_var1 {
+            self.batch_running_var = running_var1;
         }
-        count as f64 / y.shape()[0] as f64
+        Ok(())
+    }
+}
+
+/// load_named_arraysのエラー。呼び出し元の形式のエラーに変換して返す
+#[derive(Debug)]
+enum NamedArrayError {
+    Missing(String),
+    ShapeMismatch {
+        name: String,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+}
+
+/// arraysから名前がnameの配列を取り出す。形状がexpectedと異なる場合はエラーを返す
+fn named_array<D: Dimension>(
+    arrays: &[(String, ArrayD<f64>)],
+    name: &str,
+    expected: &[usize],
+) -> Result<Option<Array<f64, D>>, NamedArrayError> {
+    let shape_mismatch = |found: &[usize]| NamedArrayError::ShapeMismatch {
+        name: name.to_string(),
+        expected: expected.to_vec(),
+        found: found.to_vec(),
+    };
+    match arrays.iter().find(|(n, _)| n == name) {
+        Some((_, array)) if array.shape() != expected => Err(shape_mismatch(array.shape())),
+        Some((_, array)) => array
+            .clone()
+            .into_dimensionality()
+            .map(Some)
+            .map_err(|_| shape_mismatch(array.shape())),
+        None => Ok(None),
+    }
+}
+
+fn required_array<D: Dimension>(
+    arrays: &[(String, ArrayD<f64>)],
+    name: &str,
+    expected: &[usize],
+) -> Result<Array<f64, D>, NamedArrayError> {
+    named_array(arrays, name, expected)?.ok_or_else(|| NamedArrayError::Missing(name.to_string()))
+}
+
+impl Model for TwoLayerNet {
+    fn parameters(&mut self) -> Vec<Parameter<'_>> {
+        vec![
+            Parameter::new(
+                "w1",
+                self.w1.view_mut().into_dyn(),
+                self.grad.dw1.view_mut().into_dyn(),
+            ),
+            Parameter::new(
+                "b1",
+                self.b1.view_mut().into_dyn(),
+                self.grad.db1.view_mut().into_dyn(),
+            ),
+            Parameter::new(
+                "batch_aff",
+                self.batch_aff.view_mut().into_dyn(),
+                self.grad.dbatch_aff.view_mut().into_dyn(),
+            ),
+            Parameter::new(
+                "w2",
+                self.w2.view_mut().into_dyn(),
+                self.grad.dw2.view_mut().into_dyn(),
+            ),
+            Parameter::new(
+                "b2",
+                self.b2.view_mut().into_dyn(),
+                self.grad.db2.view_mut().into_dyn(),
+            ),
+        ]
+    }
+    fn buffers(&mut self) -> Vec<(String, ArrayViewMutD<'_, f64>)> {
+        vec![
+            (
+                "batch_running_mean".to_string(),
+                self.batch_running_mean.view_mut().into_dyn(),
+            ),
+            (
+                "batch_running_var".to_string(),
+                self.batch_running_var.view_mut().into_dyn(),
+            ),
+        ]
+    }
+}
+
+impl Network for TwoLayerNet {
+    fn predict(&mut self, x: &Array2<f64>, train_flg: bool) -> Array2<f64> {
+        TwoLayerNet::predict(self, x, train_flg)
     }
-    pub fn gradient(&mut self, x: &Array2<f64>, t: &Array2<f64>) -> TwoLayerNetGradient {
+    fn gradient_with_loss(
+        &mut self,
+        x: &Array2<f64>,
+        loss: &mut dyn Layer<Array2<f64>, f64>,
+    ) -> (f64, Array2<f64>) {
         let mut affine1 = self.create_affine1();
         let mut batch_normalization1 = self.create_batch_normalization1();
         let mut relu1 = self.create_relu1();
@@ -96,23 +328,189 @@ impl TwoLayerNet {
         let x = affine1.forward(x);
         let x = batch_normalization1.forward(&x);
         let x = relu1.forward(&x);
-        let x = affine2.forward(&x);
+        let y = affine2.forward(&x);
 
-        let mut last_layer = SoftmaxWithLossLayer::new(t);
-        last_layer.forward(&x);
+        let loss_value = loss.forward(&y);
 
         let dout = 1.0;
-        let dout = last_layer.backward(&dout);
+        let dout = loss.backward(&dout);
         let dout = affine2.backward(&dout);
         let dout = relu1.backward(&dout);
+        let dout = batch_normalization1.backward(&dout);
         affine1.backward(&dout);
 
-        TwoLayerNetGradient {
+        let grad = TwoLayerNetGradient {
             dw1: affine1.dw.clone(),
             db1: affine1.db.clone(),
             dbatch_aff: batch_normalization1.daff.clone(),
             dw2: affine2.dw.clone(),
             db2: affine2.db.clone(),
+        };
+        (self.batch_running_mean, self.batch_running_var) = (
+            batch_normalization1.running_mean,
+            batch_normalization1.running_var,
+        );
+        self.grad = grad;
+        // 正則化はself全体のパラメータを借用するため、一時的に取り出す
+        if let Some(regularization) = self.regularization.take() {
+            regularization.apply(self);
+            self.regularization = Some(regularization);
+        }
+        (loss_value + self.penalty(), y)
+    }
+    fn penalty(&mut self) -> f64 {
+        let Some(regularization) = self.regularization.take() else {
+            return 0.0;
+        };
+        let penalty = regularization.penalty(self);
+        self.regularization = Some(regularization);
+        penalty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::softmax_with_loss_layer::SoftmaxWithLossLayer;
+    use crate::subfunction::numerical_gradient::numerical_gradient;
+    use ndarray_rand::rand::{rngs::StdRng, SeedableRng};
+    use ndarray_rand::rand_distr::Normal;
+
+    fn assert_close<D: Dimension>(numerical: &Array<f64, D>, analytic: &Array<f64, D>) {
+        assert_eq!(numerical.shape(), analytic.shape());
+        for (n, a) in numerical.iter().zip(analytic.iter()) {
+            // 中心差分の誤差を考慮し、相対誤差で比較する
+            assert!(
+                (n - a).abs() <= 1e-4 * (n.abs() + a.abs()).max(1e-3),
+                "numerical: {}, analytic: {}",
+                n,
+                a
+            );
         }
     }
+
+    #[test]
+    fn gradient_matches_numerical_gradient() {
+        // cross_entropy_errorのlogに加えられる微小値の影響を抑えるため、
+        // 出力層の重みを小さくしてsoftmaxの出力が0に近づかないようにする
+        let mut rng = StdRng::seed_from_u64(0);
+        let dist = Normal::new(0.0, 1.0).unwrap();
+        let small_dist = Normal::new(0.0, 0.1).unwrap();
+        let mut network = TwoLayerNet::new(4, 5, 3, &dist);
+        network.w1 = Array2::random_using((4, 5), &dist, &mut rng);
+        network.b1 = Array1::random_using(5, &dist, &mut rng);
+        network.batch_aff = Array2::random_using((2, 5), &dist, &mut rng);
+        network.w2 = Array2::random_using((5, 3), &small_dist, &mut rng);
+        network.b2 = Array1::random_using(3, &small_dist, &mut rng);
+        let x = Array2::random_using((6, 4), &dist, &mut rng);
+        let t = Array2::from_shape_fn((6, 3), |(i, j)| if i % 3 == j { 1.0 } else { 0.0 });
+
+        // gradientと同じく学習時の振る舞いで損失を計算する
+        let loss = |network: &mut TwoLayerNet| {
+            let y = network.predict(&x, true);
+            SoftmaxWithLossLayer::new(&t).forward(&y)
+        };
+        let grad = network.clone().gradient(&x, &t);
+
+        let dw1 = numerical_gradient(
+            &|w1| {
+                let mut network = network.clone();
+                network.w1 = w1.to_owned();
+                loss(&mut network)
+            },
+            network.w1.view(),
+        );
+        let db1 = numerical_gradient(
+            &|b1| {
+                let mut network = network.clone();
+                network.b1 = b1.to_owned();
+                loss(&mut network)
+            },
+            network.b1.view(),
+        );
+        let dbatch_aff = numerical_gradient(
+            &|batch_aff| {
+                let mut network = network.clone();
+                network.batch_aff = batch_aff.to_owned();
+                loss(&mut network)
+            },
+            network.batch_aff.view(),
+        );
+        let dw2 = numerical_gradient(
+            &|w2| {
+                let mut network = network.clone();
+                network.w2 = w2.to_owned();
+                loss(&mut network)
+            },
+            network.w2.view(),
+        );
+        let db2 = numerical_gradient(
+            &|b2| {
+                let mut network = network.clone();
+                network.b2 = b2.to_owned();
+                loss(&mut network)
+            },
+            network.b2.view(),
+        );
+
+        assert_close(&dw1, &grad.dw1);
+        assert_close(&db1, &grad.db1);
+        assert_close(&dbatch_aff, &grad.dbatch_aff);
+        assert_close(&dw2, &grad.dw2);
+        assert_close(&db2, &grad.db2);
+    }
+
+    #[test]
+    fn named_arrays_round_trip_and_errors_leave_network_unchanged() {
+        let dist = Normal::new(0.0, 1.0).unwrap();
+        let mut source = TwoLayerNet::new(4, 5, 3, &dist);
+        source.batch_running_mean = Array1::linspace(-1.0, 1.0, 5);
+        let arrays = source
+            .named_arrays()
+            .into_iter()
+            .map(|(name, array)| (name, array.to_owned()))
+            .collect::<Vec<_>>();
+
+        let mut network = TwoLayerNet::new(4, 5, 3, &dist);
+        let original = network
+            .named_arrays()
+            .into_iter()
+            .map(|(_, a)| a.to_owned())
+            .collect::<Vec<_>>();
+        let unchanged = |network: &TwoLayerNet| {
+            network
+                .named_arrays()
+                .iter()
+                .zip(original.iter())
+                .all(|((_, a), b)| a == b)
+        };
+
+        let missing = arrays
+            .iter()
+            .filter(|(name, _)| name != "gamma1")
+            .cloned()
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            network.load_named_arrays(&missing),
+            Err(NamedArrayError::Missing(name)) if name == "gamma1"
+        ));
+        assert!(unchanged(&network));
+
+        let mut reshaped = arrays.clone();
+        reshaped[4].1 = ArrayD::zeros(IxDyn(&[5, 3, 1]));
+        assert!(matches!(
+            network.load_named_arrays(&reshaped),
+            Err(NamedArrayError::ShapeMismatch { name, .. }) if name == "W2"
+        ));
+        assert!(unchanged(&network));
+
+        // 移動平均はなくても読み込める
+        let without_running = arrays[..6].to_vec();
+        network.load_named_arrays(&without_running).unwrap();
+        assert_eq!(network.w1, source.w1);
+        assert_eq!(network.batch_aff, source.batch_aff);
+        assert_eq!(network.batch_running_mean, Array1::<f64>::zeros(5));
+        network.load_named_arrays(&arrays).unwrap();
+        assert_eq!(network.batch_running_mean, source.batch_running_mean);
+    }
 }